@@ -139,11 +139,90 @@ fn bench_get_100k(c: &mut Criterion) {
     });
 }
 
+// Every byte of these keys is one of 16 values, so each inner node of the tree is
+// a full Node16. Compare the SIMD lookup against the scalar one with:
+//
+//   cargo bench --bench artoo_benchmark -- node16
+//   cargo +nightly bench --bench artoo_benchmark --features nightly-simd -- node16
+fn node16_keys() -> Vec<[u8; 4]> {
+    let alphabet: Vec<u8> = (0..16).map(|i| i * 16 + 3).collect();
+    let mut keys = Vec::with_capacity(16 * 16 * 16 * 16);
+    for a in &alphabet {
+        for b in &alphabet {
+            for c in &alphabet {
+                for d in &alphabet {
+                    keys.push([*a, *b, *c, *d]);
+                }
+            }
+        }
+    }
+    keys
+}
+
+fn bench_node16(c: &mut Criterion) {
+    let keys = node16_keys();
+    let mut group = c.benchmark_group("node16");
+    group.throughput(Throughput::Elements(1));
+    group.sample_size(100);
+    group.bench_function("art_get", |b| {
+        let mut art = Art::<usize>::new();
+        for (i, key) in keys.iter().enumerate() {
+            art.insert(&key[..], i);
+        }
+        let mut i: usize = 0;
+        b.iter(|| {
+            art.get(&keys[i % keys.len()][..]);
+            i += 7919;
+        });
+    });
+
+    group.bench_function("art_insert_remove", |b| {
+        let mut art = Art::<usize>::new();
+        for (i, key) in keys.iter().enumerate() {
+            art.insert(&key[..], i);
+        }
+        let mut i: usize = 0;
+        b.iter(|| {
+            let key = &keys[i % keys.len()][..];
+            let val = art.remove(key).unwrap();
+            art.insert(key, val);
+            i += 7919;
+        });
+    });
+
+    group.bench_function("btree_get", |b| {
+        let mut btree = BTree::<[u8; 4], usize>::new();
+        for (i, key) in keys.iter().enumerate() {
+            btree.insert(*key, i);
+        }
+        let mut i: usize = 0;
+        b.iter(|| {
+            btree.get(&keys[i % keys.len()]);
+            i += 7919;
+        });
+    });
+}
+
+fn bench_build_100k(c: &mut Criterion) {
+    let n = 100_000;
+    let mut group = c.benchmark_group("build_100k");
+    group.sample_size(20);
+    group.bench_function("art_insert", |b| b.iter(|| art_new(n)));
+    group.bench_function("art_from_sorted_iter", |b| {
+        b.iter(|| Art::from_sorted_iter((0..n).map(|i| (i.to_be_bytes(), i))))
+    });
+    group.bench_function("btree_from_iter", |b| {
+        b.iter(|| (0..n).map(|i| (i, i)).collect::<BTree<usize, usize>>())
+    });
+}
+
 criterion_group!(
     benches,
     // bench_new,
     bench_insert_100k,
     bench_get_100k,
+    bench_node16,
+    bench_build_100k,
     // bench_get_10m,
 );
 criterion_main!(benches);
@@ -0,0 +1,289 @@
+use crate::Key;
+
+/// A type that encodes into binary-comparable bytes.
+///
+/// Comparing two encodings byte by byte, with a shorter prefix sorting first, must
+/// give the same order as comparing the original values. That is what lets a
+/// `TypedTree` iterate in the order of the key type.
+pub trait ArtKey {
+    /// Appends the encoding of the key to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Calls `f` with the encoding of the key.
+    ///
+    /// Implementations override this when the encoding can be produced without
+    /// allocating, which is the common case for lookups.
+    fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        f(&out)
+    }
+
+    fn to_key(&self) -> Key {
+        self.with_encoded(|bytes| Key::from(bytes))
+    }
+}
+
+/// A type that decodes from the bytes produced by its `ArtKey` encoding.
+pub trait FromArtKey: Sized {
+    /// Returns `None` if `bytes` is not a valid encoding of the type.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+impl<K: ArtKey + ?Sized> ArtKey for &K {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (**self).encode(out)
+    }
+
+    fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        (**self).with_encoded(f)
+    }
+}
+
+// big-endian bytes of an unsigned integer already sort like the integer.
+macro_rules! art_key_for_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl ArtKey for $ty {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_be_bytes());
+                }
+
+                fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+                    f(&self.to_be_bytes())
+                }
+            }
+
+            impl FromArtKey for $ty {
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    Some(<$ty>::from_be_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+art_key_for_unsigned!(u8, u16, u32, u64, u128, usize);
+
+// flipping the sign bit moves the negative numbers below the positive ones while
+// two's complement keeps both halves in order.
+macro_rules! art_key_for_signed {
+    ($($ty:ty => $unsigned:ty),*) => {
+        $(
+            impl ArtKey for $ty {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    self.with_encoded(|bytes| out.extend_from_slice(bytes))
+                }
+
+                fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+                    let flipped = (*self as $unsigned) ^ (1 << (<$unsigned>::BITS - 1));
+                    f(&flipped.to_be_bytes())
+                }
+            }
+
+            impl FromArtKey for $ty {
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    let flipped = <$unsigned>::from_be_bytes(bytes.try_into().ok()?);
+                    Some((flipped ^ (1 << (<$unsigned>::BITS - 1))) as $ty)
+                }
+            }
+        )*
+    };
+}
+
+art_key_for_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+// the bits of a float sort like `total_cmp` once negative numbers have every bit
+// flipped, which reverses their order, and positive numbers have the sign bit set.
+macro_rules! art_key_for_float {
+    ($($ty:ty => $bits:ty),*) => {
+        $(
+            impl ArtKey for $ty {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    self.with_encoded(|bytes| out.extend_from_slice(bytes))
+                }
+
+                fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+                    let bits = self.to_bits();
+                    let sign = 1 << (<$bits>::BITS - 1);
+                    let ordered = if bits & sign == 0 { bits | sign } else { !bits };
+                    f(&ordered.to_be_bytes())
+                }
+            }
+
+            impl FromArtKey for $ty {
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    let ordered = <$bits>::from_be_bytes(bytes.try_into().ok()?);
+                    let sign = 1 << (<$bits>::BITS - 1);
+                    let bits = if ordered & sign == 0 { !ordered } else { ordered ^ sign };
+                    Some(<$ty>::from_bits(bits))
+                }
+            }
+        )*
+    };
+}
+
+art_key_for_float!(f32 => u32, f64 => u64);
+
+// byte strings and UTF-8 already compare like their bytes.
+impl ArtKey for [u8] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        f(self)
+    }
+}
+
+impl ArtKey for Vec<u8> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        f(self)
+    }
+}
+
+impl FromArtKey for Vec<u8> {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl<const N: usize> ArtKey for [u8; N] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        f(self)
+    }
+}
+
+impl<const N: usize> FromArtKey for [u8; N] {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok()
+    }
+}
+
+impl ArtKey for str {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        f(self.as_bytes())
+    }
+}
+
+impl ArtKey for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        f(self.as_bytes())
+    }
+}
+
+impl FromArtKey for String {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl ArtKey for Key {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_slice());
+    }
+
+    fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        f(self.as_slice())
+    }
+
+    fn to_key(&self) -> Key {
+        self.clone()
+    }
+}
+
+impl FromArtKey for Key {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(Key::from(bytes))
+    }
+}
+
+#[cfg(test)]
+fn assert_encoding_preserves_order<K>(mut values: Vec<K>)
+where
+    K: ArtKey + FromArtKey + PartialEq + std::fmt::Debug,
+{
+    for (a, b) in values.iter().zip(values.iter().skip(1)) {
+        assert!(
+            a.to_key() < b.to_key(),
+            "{:?} does not encode below {:?}",
+            a,
+            b
+        );
+    }
+    for value in values.drain(..) {
+        let decoded = K::decode(&value.to_key());
+        assert_eq!(decoded.as_ref(), Some(&value));
+    }
+}
+
+#[test]
+fn test_art_key_integers_sort_like_ord() {
+    assert_encoding_preserves_order(vec![0u8, 1, 127, 128, 255]);
+    assert_encoding_preserves_order(vec![0u64, 1, 255, 256, u32::MAX as u64, u64::MAX]);
+    assert_encoding_preserves_order(vec![i8::MIN, -1, 0, 1, i8::MAX]);
+    assert_encoding_preserves_order(vec![i32::MIN, -65_536, -256, -1, 0, 1, 256, i32::MAX]);
+    assert_encoding_preserves_order(vec![i64::MIN, -1, 0, i64::MAX]);
+    assert_encoding_preserves_order(vec![i128::MIN, -1, 0, 1, i128::MAX]);
+    assert_encoding_preserves_order(vec![isize::MIN, 0, isize::MAX]);
+    assert_eq!(u32::decode(&[0, 1]), None);
+}
+
+#[test]
+fn test_art_key_floats_sort_like_total_cmp() {
+    let values = vec![
+        f64::NEG_INFINITY,
+        f64::MIN,
+        -1.5,
+        -f64::MIN_POSITIVE,
+        -0.0,
+        0.0,
+        f64::MIN_POSITIVE,
+        1.0,
+        1.5,
+        f64::MAX,
+        f64::INFINITY,
+    ];
+    for (a, b) in values.iter().zip(values.iter().skip(1)) {
+        assert_eq!(a.total_cmp(b), a.to_key().cmp(&b.to_key()));
+    }
+    for value in &values {
+        let decoded = f64::decode(&value.to_key()).unwrap();
+        assert_eq!(decoded.to_bits(), value.to_bits());
+    }
+    assert!(f64::NAN.to_key() > f64::INFINITY.to_key());
+    assert!((-f64::NAN).to_key() < f64::NEG_INFINITY.to_key());
+    assert_encoding_preserves_order(vec![f32::NEG_INFINITY, -1.0f32, 0.0, 1.0, f32::INFINITY]);
+}
+
+#[test]
+fn test_art_key_byte_strings_sort_like_ord() {
+    assert_encoding_preserves_order(vec![
+        String::new(),
+        String::from("a"),
+        String::from("ab"),
+        String::from("b"),
+        String::from("é"),
+    ]);
+    assert_encoding_preserves_order(vec![vec![], vec![0u8], vec![0, 0], vec![1]]);
+    assert_encoding_preserves_order(vec![[0u8, 9], [1, 0], [1, 1]]);
+    assert_encoding_preserves_order(vec![Key::from(""), Key::from("a"), Key::from("b")]);
+    assert_eq!("abc".to_key(), String::from("abc").to_key());
+    assert_eq!(String::decode(&[0xff]), None);
+}
@@ -0,0 +1,338 @@
+use crate::{Key, Node, Tree};
+use std::fmt;
+
+/// A state machine over key bytes that `Tree::search` runs down the tree.
+///
+/// The search feeds every key byte to `accept` on the way down and yields the
+/// keys whose final state `is_match`. A subtree is skipped as soon as its state
+/// fails `can_match`, so an automaton that gives up early keeps the walk small.
+pub trait Automaton {
+    type State: Clone;
+
+    /// The state before any byte of the key.
+    fn start(&self) -> Self::State;
+
+    /// Whether a key that ends in `state` is in the language.
+    fn is_match(&self, state: &Self::State) -> bool;
+
+    /// Whether any key extending the bytes so far could still match. Returning
+    /// `true` is always correct, only slower.
+    fn can_match(&self, _state: &Self::State) -> bool {
+        true
+    }
+
+    /// The state after one more key byte.
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State;
+}
+
+impl<A: Automaton> Automaton for &A {
+    type State = A::State;
+
+    fn start(&self) -> Self::State {
+        (*self).start()
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        (*self).is_match(state)
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        (*self).can_match(state)
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        (*self).accept(state, byte)
+    }
+}
+
+/// The error for a `Glob` or `Regex` pattern that does not parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    pos: usize,
+    reason: &'static str,
+}
+
+impl PatternError {
+    pub(crate) fn new(pos: usize, reason: &'static str) -> PatternError {
+        PatternError { pos, reason }
+    }
+
+    /// The byte offset in the pattern where parsing failed.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid pattern at byte {}: {}", self.pos, self.reason)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// A set of bytes, one bit each.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    pub fn empty() -> ByteSet {
+        ByteSet([0; 4])
+    }
+
+    pub fn full() -> ByteSet {
+        ByteSet([!0; 4])
+    }
+
+    pub fn byte(byte: u8) -> ByteSet {
+        let mut set = ByteSet::empty();
+        set.insert(byte);
+        set
+    }
+
+    pub fn insert(&mut self, byte: u8) {
+        self.0[byte as usize / 64] |= 1 << (byte % 64);
+    }
+
+    pub fn insert_range(&mut self, lo: u8, hi: u8) {
+        for byte in lo..=hi {
+            self.insert(byte);
+        }
+    }
+
+    pub fn contains(&self, byte: u8) -> bool {
+        self.0[byte as usize / 64] & (1 << (byte % 64)) != 0
+    }
+
+    pub fn negate(&mut self) {
+        for word in &mut self.0 {
+            *word = !*word;
+        }
+    }
+
+    /// Parses a bracketed class such as `[a-z_]` whose `[` is at `pos`, returning
+    /// the set and the position after the closing `]`.
+    ///
+    /// A leading byte from `negate` inverts the class, a `]` right after the
+    /// opening (or the negation) is a literal and `\` escapes the next byte.
+    pub fn parse_class(
+        pattern: &[u8],
+        pos: usize,
+        negate: &[u8],
+    ) -> Result<(ByteSet, usize), PatternError> {
+        let mut set = ByteSet::empty();
+        let mut i = pos + 1;
+        let negated = pattern.get(i).is_some_and(|b| negate.contains(b));
+        if negated {
+            i += 1;
+        }
+        let first = i;
+        let literal = |i: &mut usize| -> Result<u8, PatternError> {
+            let byte = match pattern.get(*i) {
+                Some(b'\\') => {
+                    *i += 1;
+                    pattern.get(*i)
+                }
+                byte => byte,
+            };
+            *i += 1;
+            byte.copied()
+                .ok_or(PatternError::new(pos, "unclosed character class"))
+        };
+        loop {
+            match pattern.get(i) {
+                None => return Err(PatternError::new(pos, "unclosed character class")),
+                Some(b']') if i > first => break,
+                _ => (),
+            }
+            let lo = literal(&mut i)?;
+            if pattern.get(i) == Some(&b'-') && pattern.get(i + 1).is_some_and(|b| *b != b']') {
+                i += 1;
+                let hi = literal(&mut i)?;
+                if hi < lo {
+                    return Err(PatternError::new(i - 1, "range out of order"));
+                }
+                set.insert_range(lo, hi);
+            } else {
+                set.insert(lo);
+            }
+        }
+        if negated {
+            set.negate();
+        }
+        Ok((set, i + 1))
+    }
+}
+
+impl<V> Tree<V> {
+    /// Iterates the entries whose keys `automaton` matches, in key order.
+    ///
+    /// Each child byte and each byte of a compressed path is fed to the automaton
+    /// on the way down and a subtree is left out once its state can no longer
+    /// match. For a query like `user:*:session` that skips every key not starting
+    /// with `user:`, but the `*` can still match at any byte, so each key below
+    /// `user:` is walked to its end.
+    ///
+    /// ```
+    /// use artoo::{Glob, Tree};
+    ///
+    /// let mut tree = Tree::new();
+    /// tree.insert(b"user:1:session", "a");
+    /// tree.insert(b"user:1:profile", "b");
+    /// tree.insert(b"user:22:session", "c");
+    /// tree.insert(b"admin:1:session", "d");
+    ///
+    /// let glob = Glob::new("user:*:session").unwrap();
+    /// let found: Vec<&str> = tree.search(glob).map(|(_, v)| *v).collect();
+    /// assert_eq!(found, ["a", "c"]);
+    /// ```
+    pub fn search<A: Automaton>(&self, automaton: A) -> Search<'_, V, A> {
+        let start = automaton.start();
+        let stack = match automaton.can_match(&start) {
+            true => vec![(Key::Empty, &self.root, start)],
+            false => vec![],
+        };
+        Search { automaton, stack }
+    }
+}
+
+/// An iterator over the entries of a `Tree` that an `Automaton` matches.
+pub struct Search<'a, V, A: Automaton> {
+    automaton: A,
+    /// Nodes still to visit, the next one on top, with their key and the state the
+    /// key leads to.
+    stack: Vec<(Key, &'a Node<V>, A::State)>,
+}
+
+impl<'a, V, A: Automaton> Search<'a, V, A> {
+    /// The next match along with the state it ended in.
+    pub(crate) fn next_with_state(&mut self) -> Option<(Key, &'a V, A::State)> {
+        let automaton = &self.automaton;
+        'stack: loop {
+            let (key, node, state) = self.stack.pop()?;
+            match node {
+                Node::None => (),
+                Node::Leaf(leaf) => {
+                    if automaton.is_match(&state) {
+                        return Some((key, &leaf.val, state));
+                    }
+                }
+                Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => {
+                    // reversed so that the smallest byte is popped first.
+                    for (byte, child) in bn.iter().rev() {
+                        let next = automaton.accept(&state, byte);
+                        if automaton.can_match(&next) {
+                            self.stack.push((key.concat(byte), child, next));
+                        }
+                    }
+                    if let Some(leaf) = node.leaf() {
+                        if automaton.is_match(&state) {
+                            return Some((key, &leaf.val, state));
+                        }
+                    }
+                }
+                Node::Branch(branch) => {
+                    let mut state = state;
+                    for byte in branch.sequence() {
+                        state = automaton.accept(&state, *byte);
+                        if !automaton.can_match(&state) {
+                            continue 'stack;
+                        }
+                    }
+                    let key = key.concat_slice(branch.sequence());
+                    self.stack.push((key, &branch.node, state));
+                }
+            }
+        }
+    }
+}
+
+impl<'a, V, A: Automaton> Iterator for Search<'a, V, A> {
+    type Item = (Key, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_state().map(|(key, val, _)| (key, val))
+    }
+}
+
+#[cfg(test)]
+struct Counting<'c, A> {
+    inner: A,
+    accepted: &'c std::cell::Cell<usize>,
+}
+
+#[cfg(test)]
+impl<'c, A: Automaton> Automaton for Counting<'c, A> {
+    type State = A::State;
+
+    fn start(&self) -> Self::State {
+        self.inner.start()
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        self.inner.is_match(state)
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        self.inner.can_match(state)
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        self.accepted.set(self.accepted.get() + 1);
+        self.inner.accept(state, byte)
+    }
+}
+
+#[test]
+fn test_byte_set_parse_class() {
+    let parse = |p: &str| ByteSet::parse_class(p.as_bytes(), 0, b"!^");
+    let (set, end) = parse("[a-c_]x").unwrap();
+    assert_eq!(end, 6);
+    assert!((b'a'..=b'c').all(|b| set.contains(b)) && set.contains(b'_'));
+    assert!(!set.contains(b'd') && !set.contains(b'x'));
+
+    let (set, _) = parse("[!]\\-]").unwrap();
+    assert!(!set.contains(b']') && !set.contains(b'-') && set.contains(b'a'));
+    let (set, _) = parse("[a-]").unwrap();
+    assert!(set.contains(b'a') && set.contains(b'-') && !set.contains(b'b'));
+
+    assert_eq!(parse("[abc").err().map(|e| e.pos()), Some(0));
+    assert_eq!(parse("[]").err().map(|e| e.pos()), Some(0));
+    assert_eq!(parse("[z-a]").err().map(|e| e.pos()), Some(3));
+}
+
+#[test]
+fn test_search_prunes_subtrees() {
+    use crate::Glob;
+    use std::cell::Cell;
+
+    let mut tree = Tree::new();
+    for user in 0..1_000u32 {
+        for field in ["profile", "session", "settings"] {
+            tree.insert(format!("user:{}:{}", user, field).as_bytes(), user);
+        }
+        tree.insert(format!("group:{}:session", user).as_bytes(), user);
+    }
+    let accepted = Cell::new(0);
+    let glob = Glob::new("user:*:session").unwrap();
+    let counting = Counting {
+        inner: &glob,
+        accepted: &accepted,
+    };
+    let found: Vec<u32> = tree.search(counting).map(|(_, v)| *v).collect();
+    let mut expected: Vec<u32> = (0..1_000).collect();
+    expected.sort_by_key(|u| format!("user:{}:session", u));
+    assert_eq!(found, expected);
+    // every byte below `user:` is fed to the automaton once, while the `g` of
+    // `group:` is the only byte of that subtree it sees.
+    let mut below_user = std::collections::BTreeSet::new();
+    for key in tree.keys().filter(|k| k.starts_with(b"user:")) {
+        below_user.extend((1..=key.len()).map(|len| key[..len].to_vec()));
+    }
+    assert_eq!(accepted.get(), below_user.len() + 1);
+
+    // a pattern that no key matches finds nothing.
+    let none = Glob::new("[]]x").unwrap();
+    assert_eq!(tree.search(&none).count(), 0);
+    assert_eq!(Tree::<()>::new().search(&glob).count(), 0);
+}
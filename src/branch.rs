@@ -1,10 +1,20 @@
 use crate::describe::{Describe, Describer};
 // use crate::{Leaf, Node, Seek};
-use crate::Node;
+use crate::{Key, Node};
 
+/// A Branch is a compressed path: a run of bytes with no other keys branching off
+/// of it, followed by the node that the run leads to.
+///
+/// The `node` of a Branch is never `Node::None` or another `Node::Branch` and the
+/// `sequence` is never empty.
+///
+/// A key inserted into an empty part of the tree is stored lazily as a Branch
+/// holding the rest of the key and its `Leaf`. Short sequences are kept inline by
+/// `Key`, so such a subtree costs a single allocation until another key diverges
+/// from it.
 pub(crate) struct Branch<V> {
-    sequence: Box<[u8]>,
-    node: Node<V>,
+    pub sequence: Key,
+    pub node: Node<V>,
 }
 
 impl<V> std::fmt::Debug for Branch<V>
@@ -31,7 +41,7 @@ impl<V> Describer for Branch<V> {
 impl<V> Branch<V> {
     pub fn new(sequence: &[u8], node: Node<V>) -> Self {
         Self {
-            sequence: sequence.to_vec().into_boxed_slice(),
+            sequence: Key::from(sequence),
             node,
         }
     }
@@ -40,6 +50,15 @@ impl<V> Branch<V> {
         &self.sequence
     }
 
+    /// The number of leading bytes that the sequence shares with `key`.
+    pub fn common_prefix_len(&self, key: &[u8]) -> usize {
+        self.sequence
+            .iter()
+            .zip(key)
+            .take_while(|(a, b)| a == b)
+            .count()
+    }
+
     // pub fn find_child<'a>(&self, seek: Seek<'a>) -> FindChild<'a, &Node<V>> {
     //     // let seq = self.sequence();
     //     // let len = seq.len();
@@ -0,0 +1,320 @@
+use crate::{BoxNode, Leaf, Node, Tree};
+
+impl<V> Tree<V> {
+    /// Builds a tree from `(key, value)` pairs in ascending key order.
+    ///
+    /// The tree is built bottom-up straight from the iterator, with every inner node
+    /// created at its final size, so nothing is grown or copied along the way. From
+    /// the first pair that does not sort after the one before it on, the pairs are
+    /// inserted one at a time, which makes this the same as inserting every pair in
+    /// order.
+    ///
+    /// ```
+    /// let tree = artoo::Tree::from_sorted_iter((0..1_000u32).map(|i| (i.to_be_bytes(), i)));
+    /// assert_eq!(tree.len(), 1_000);
+    /// assert_eq!(tree.get(&7u32.to_be_bytes()), Some(&7));
+    /// ```
+    pub fn from_sorted_iter<K, I>(iter: I) -> Tree<V>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut iter = iter.into_iter();
+        let mut builder = Builder { open: Vec::new() };
+        let mut count = 0;
+        let mut unsorted = None;
+        for (key, val) in iter.by_ref() {
+            match builder.shared(key.as_ref()) {
+                Some(shared) => builder.push(key, val, shared),
+                None => {
+                    unsorted = Some((key, val));
+                    break;
+                }
+            }
+            count += 1;
+        }
+        let mut tree = Tree {
+            root: builder.finish(),
+            count,
+        };
+        for (key, val) in unsorted.into_iter().chain(iter) {
+            tree.insert(key.as_ref(), val);
+        }
+        tree
+    }
+}
+
+/// Builds a tree from sorted keys, one key at a time.
+///
+/// `open` holds the nodes that may still get children: the leaf of the last key
+/// and the nodes above it, deepest last. A new key closes every open node deeper
+/// than the prefix it shares with the last key, so each node is closed with all of
+/// its children known.
+struct Builder<K, V> {
+    open: Vec<Open<K, V>>,
+}
+
+/// A node under construction. Its bytes above `depth` are those of `key`.
+struct Open<K, V> {
+    depth: usize,
+    key: K,
+    leaf: Option<Leaf<V>>,
+    children: Vec<(u8, Node<V>)>,
+}
+
+impl<K: AsRef<[u8]>, V> Builder<K, V> {
+    /// Returns how many bytes `key` shares with the last key, or None if it does
+    /// not sort after it.
+    fn shared(&self, key: &[u8]) -> Option<usize> {
+        let last = match self.open.last() {
+            Some(top) => top.key.as_ref(),
+            None => return Some(0),
+        };
+        match key > last {
+            true => Some(key.iter().zip(last).take_while(|(a, b)| a == b).count()),
+            false => None,
+        }
+    }
+
+    fn push(&mut self, key: K, val: V, shared: usize) {
+        let mut closed = None;
+        while matches!(self.open.last(), Some(top) if top.depth > shared) {
+            let mut top = self.open.pop().unwrap();
+            if let Some(child) = closed.take() {
+                top.add_child(child);
+            }
+            closed = Some(top);
+        }
+        if let Some(child) = closed {
+            match self.open.last_mut() {
+                Some(top) if top.depth == shared => top.add_child(child),
+                // the new key splits off below the closed nodes, at a new node.
+                _ => {
+                    let (child, key) = child.close(shared);
+                    self.open.push(Open {
+                        depth: shared,
+                        key,
+                        leaf: None,
+                        children: vec![child],
+                    });
+                }
+            }
+        }
+        self.open.push(Open {
+            depth: key.as_ref().len(),
+            key,
+            leaf: Some(Leaf::new(val)),
+            children: Vec::new(),
+        });
+    }
+
+    fn finish(mut self) -> Node<V> {
+        let mut closed = None;
+        while let Some(mut top) = self.open.pop() {
+            if let Some(child) = closed.take() {
+                top.add_child(child);
+            }
+            closed = Some(top);
+        }
+        match closed {
+            Some(root) => {
+                let depth = root.depth;
+                let (node, key) = root.into_node();
+                Node::with_prefix(&key.as_ref()[..depth], node)
+            }
+            None => Node::None,
+        }
+    }
+}
+
+impl<K: AsRef<[u8]>, V> Open<K, V> {
+    fn add_child(&mut self, child: Open<K, V>) {
+        let (child, _) = child.close(self.depth);
+        self.children.push(child);
+    }
+
+    /// Turns this into the child of a node at `depth`, behind its byte and the
+    /// bytes compressed in between.
+    fn close(self, depth: usize) -> ((u8, Node<V>), K) {
+        let end = self.depth;
+        let (node, key) = self.into_node();
+        let bytes = key.as_ref();
+        let child = (
+            bytes[depth],
+            Node::with_prefix(&bytes[depth + 1..end], node),
+        );
+        (child, key)
+    }
+
+    fn into_node(self) -> (Node<V>, K) {
+        if self.children.is_empty() {
+            return (Node::Leaf(self.leaf.unwrap()), self.key);
+        }
+        let capacity = match self.children.len() {
+            0..=4 => 4,
+            5..=16 => 16,
+            17..=48 => 48,
+            _ => 256,
+        };
+        let bn = BoxNode::with_children(capacity, self.children);
+        let node = match self.leaf {
+            Some(leaf) => Node::BoxNodeLeaf(bn, leaf),
+            None => Node::BoxNode(bn),
+        };
+        (node, self.key)
+    }
+}
+
+impl<K: AsRef<[u8]>, V> FromIterator<(K, V)> for Tree<V> {
+    /// Sorts the pairs if needed and builds the tree with `from_sorted_iter`.
+    ///
+    /// A repeated key keeps the last of its values, like inserting in order.
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Tree<V> {
+        let mut entries: Vec<(K, V)> = iter.into_iter().collect();
+        if !entries
+            .windows(2)
+            .all(|pair| pair[0].0.as_ref() < pair[1].0.as_ref())
+        {
+            // a stable sort keeps repeated keys in input order for the dedup.
+            entries.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+            entries = dedup_keep_last(entries);
+        }
+        Tree::from_sorted_iter(entries)
+    }
+}
+
+fn dedup_keep_last<K: AsRef<[u8]>, V>(entries: Vec<(K, V)>) -> Vec<(K, V)> {
+    let mut out: Vec<(K, V)> = Vec::with_capacity(entries.len());
+    for (key, val) in entries {
+        match out.last_mut() {
+            Some(last) if last.0.as_ref() == key.as_ref() => last.1 = val,
+            _ => out.push((key, val)),
+        }
+    }
+    out
+}
+
+impl<K: AsRef<[u8]>, V> Extend<(K, V)> for Tree<V> {
+    /// Inserts every pair, in any order. An empty tree is bulk loaded instead.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        if self.is_empty() {
+            *self = Tree::from_iter(iter);
+            return;
+        }
+        for (key, val) in iter {
+            self.insert(key.as_ref(), val);
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::describe::{Describe, Describer};
+
+#[cfg(test)]
+fn describe<V>(tree: &Tree<V>) -> String {
+    let mut desc = Describe::new();
+    tree.describe(&mut desc);
+    desc.string
+}
+
+#[test]
+fn test_bulk_load_matches_inserting() {
+    let mut keys: Vec<Vec<u8>> = vec![];
+    for i in 0..70_000u64 {
+        keys.push((i * 13).to_be_bytes().to_vec());
+    }
+    for word in [
+        "",
+        "a",
+        "ab",
+        "abc",
+        "b",
+        "bcdefghijklmnop",
+        "bcdefghijklmnoq",
+    ] {
+        keys.push(word.as_bytes().to_vec());
+    }
+    keys.sort();
+    let mut inserted = Tree::<usize>::new();
+    for (i, key) in keys.iter().enumerate() {
+        inserted.insert(key, i);
+    }
+    let loaded = Tree::from_sorted_iter(keys.iter().enumerate().map(|(i, k)| (k, i)));
+    assert_eq!(loaded.len(), inserted.len());
+    assert!(loaded.iter().eq(inserted.iter()));
+    // every node is picked at the size inserting would have grown it to.
+    assert_eq!(describe(&loaded), describe(&inserted));
+}
+
+#[test]
+fn test_bulk_load_picks_node_sizes_up_front() {
+    for (n, expected) in [
+        (1, "Branch"),
+        (3, "Node4"),
+        (10, "Node16"),
+        (40, "Node48"),
+        (200, "Node256"),
+    ] {
+        let tree = Tree::from_sorted_iter((0..n as u8).map(|b| ([b], b)));
+        let root = match &tree.root {
+            Node::Branch(_) => "Branch",
+            Node::BoxNode(BoxNode::Node4(_)) => "Node4",
+            Node::BoxNode(BoxNode::Node16(_)) => "Node16",
+            Node::BoxNode(BoxNode::Node48(_)) => "Node48",
+            Node::BoxNode(BoxNode::Node256(_)) => "Node256",
+            _ => "other",
+        };
+        assert_eq!(root, expected, "{} keys", n);
+        assert!(tree.iter().map(|(_, v)| *v).eq(0..n as u8));
+    }
+    let empty = Tree::<u8>::from_sorted_iter(Vec::<(Vec<u8>, u8)>::new());
+    assert!(empty.is_empty());
+    assert!(empty.root.is_none());
+}
+
+#[test]
+fn test_from_iter_and_extend_take_any_order() {
+    let words = ["pear", "fig", "apple", "fig", "kiwi", "apple"];
+    let tree: Tree<usize> = words.iter().enumerate().map(|(i, w)| (w, i)).collect();
+    assert_eq!(tree.len(), 4);
+    // the last of a repeated key wins, like inserting in order.
+    assert_eq!(tree.get(b"apple"), Some(&5));
+    assert_eq!(tree.get(b"fig"), Some(&3));
+    let keys: Vec<String> = tree
+        .keys()
+        .map(|k| String::from_utf8(k.to_vec()).unwrap())
+        .collect();
+    assert_eq!(keys, vec!["apple", "fig", "kiwi", "pear"]);
+
+    let mut tree = tree;
+    tree.extend([("banana", 10), ("apple", 11)]);
+    assert_eq!(tree.len(), 5);
+    assert_eq!(tree.get(b"apple"), Some(&11));
+    let mut empty = Tree::new();
+    empty.extend((0..100u16).rev().map(|i| (i.to_be_bytes(), i)));
+    assert!(empty.values().copied().eq(0..100));
+}
+
+#[test]
+fn test_bulk_load_inserts_from_the_first_unsorted_pair_on() {
+    let long = vec![7u8; 100_000];
+    let mut longer = long.clone();
+    longer.push(1);
+    let pairs: Vec<(Vec<u8>, usize)> = vec![
+        (b"a".to_vec(), 0),
+        (long.clone(), 1),
+        (longer, 2),
+        (b"b".to_vec(), 3),
+        (long, 4),
+        (b"a".to_vec(), 5),
+        (b"c".to_vec(), 6),
+    ];
+    let mut inserted = Tree::new();
+    for (key, val) in &pairs {
+        inserted.insert(key, *val);
+    }
+    let loaded = Tree::from_sorted_iter(pairs);
+    assert_eq!(loaded.len(), 5);
+    assert!(loaded.iter().eq(inserted.iter()));
+    assert_eq!(describe(&loaded), describe(&inserted));
+}
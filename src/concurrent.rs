@@ -0,0 +1,1351 @@
+//! An adaptive radix tree for many concurrent readers and writers.
+//!
+//! `ConcurrentTree` uses optimistic lock coupling from "The ART of Practical
+//! Synchronization" (Leis et al.). Every inner node has a version lock. Readers
+//! never write to shared memory: they read a node's version, read the node and
+//! check that the version did not change, restarting from the root when it did.
+//! Writers upgrade the versions they read into write locks, so only the one or
+//! two nodes being changed are locked and writers in other parts of the tree go
+//! ahead in parallel.
+//!
+//! Nodes that are replaced or unlinked may still be read by threads that loaded
+//! them before, so they are retired to an epoch based collector and freed once
+//! every thread that could have seen them has moved on.
+//!
+//! The nodes mirror the `Node4`, `Node16`, `Node48` and `Node256` layouts with
+//! atomic keys and child pointers. Inner nodes keep their compressed path in an
+//! immutable prefix and a node whose prefix has to change is replaced by a copy.
+//! Leaves hold their whole key, so a key is stored as a single leaf until another
+//! key diverges from it.
+
+use std::cell::RefCell;
+use std::collections::hash_map::HashMap;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicPtr, AtomicU16, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+const KIND_LEAF: u8 = 0;
+const KIND_N4: u8 = 1;
+const KIND_N16: u8 = 2;
+const KIND_N48: u8 = 3;
+const KIND_N256: u8 = 4;
+
+const LOCKED: u64 = 0b10;
+const OBSOLETE: u64 = 0b01;
+
+/// The version was changed by a writer, so the operation starts over.
+struct Restart;
+
+/// The start of every node. A pointer to any node is a pointer to its header.
+#[repr(C)]
+struct Header<V> {
+    kind: u8,
+    /// A version lock: bit 0 marks the node obsolete, bit 1 write locked and the
+    /// rest counts the writes. Unused by leaves, which never change.
+    version: AtomicU64,
+    /// The compressed path in front of the children of an inner node.
+    prefix: Box<[u8]>,
+    /// The leaf of the key that ends at this inner node.
+    leaf: AtomicPtr<Header<V>>,
+}
+
+#[repr(C)]
+struct Leaf<V> {
+    header: Header<V>,
+    key: Box<[u8]>,
+    val: V,
+}
+
+#[repr(C)]
+struct N4<V> {
+    header: Header<V>,
+    count: AtomicU16,
+    keys: [AtomicU8; 4],
+    children: [AtomicPtr<Header<V>>; 4],
+}
+
+#[repr(C)]
+struct N16<V> {
+    header: Header<V>,
+    count: AtomicU16,
+    keys: [AtomicU8; 16],
+    children: [AtomicPtr<Header<V>>; 16],
+}
+
+#[repr(C)]
+struct N48<V> {
+    header: Header<V>,
+    count: AtomicU16,
+    /// One more than the slot in `children` of each byte, zero for no child.
+    index: [AtomicU8; 256],
+    children: [AtomicPtr<Header<V>>; 48],
+}
+
+#[repr(C)]
+struct N256<V> {
+    header: Header<V>,
+    count: AtomicU16,
+    children: [AtomicPtr<Header<V>>; 256],
+}
+
+impl<V> Header<V> {
+    fn new(kind: u8, prefix: &[u8], leaf: *mut Header<V>) -> Header<V> {
+        Header {
+            kind,
+            version: AtomicU64::new(0),
+            prefix: prefix.into(),
+            leaf: AtomicPtr::new(leaf),
+        }
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.kind == KIND_LEAF
+    }
+
+    fn read_lock(&self) -> Result<u64, Restart> {
+        let version = self.version.load(Ordering::Acquire);
+        if version & (LOCKED | OBSOLETE) != 0 {
+            std::hint::spin_loop();
+            return Err(Restart);
+        }
+        Ok(version)
+    }
+
+    /// Checks that nothing was written to the node since `version` was read.
+    fn check(&self, version: u64) -> Result<(), Restart> {
+        fence(Ordering::Acquire);
+        match self.version.load(Ordering::Relaxed) == version {
+            true => Ok(()),
+            false => Err(Restart),
+        }
+    }
+
+    fn upgrade(&self, version: u64) -> Result<(), Restart> {
+        self.version
+            .compare_exchange(
+                version,
+                version + LOCKED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .map(|_| ())
+            .map_err(|_| Restart)
+    }
+
+    fn unlock(&self) {
+        self.version.fetch_add(LOCKED, Ordering::Release);
+    }
+
+    /// Unlocks a node that has been unlinked from the tree, which restarts every
+    /// reader still on it.
+    fn unlock_obsolete(&self) {
+        self.version.fetch_add(LOCKED + OBSOLETE, Ordering::Release);
+    }
+}
+
+fn new_leaf<V>(key: &[u8], val: V) -> *mut Header<V> {
+    let leaf = Box::new(Leaf {
+        header: Header::new(KIND_LEAF, &[], ptr::null_mut()),
+        key: key.into(),
+        val,
+    });
+    Box::into_raw(leaf) as *mut Header<V>
+}
+
+fn new_inner<V>(kind: u8, prefix: &[u8], leaf: *mut Header<V>) -> *mut Header<V> {
+    let header = Header::new(kind, prefix, leaf);
+    let count = AtomicU16::new(0);
+    match kind {
+        KIND_N4 => Box::into_raw(Box::new(N4 {
+            header,
+            count,
+            keys: Default::default(),
+            children: Default::default(),
+        })) as *mut Header<V>,
+        KIND_N16 => Box::into_raw(Box::new(N16 {
+            header,
+            count,
+            keys: Default::default(),
+            children: Default::default(),
+        })) as *mut Header<V>,
+        KIND_N48 => Box::into_raw(Box::new(N48 {
+            header,
+            count,
+            index: array_init::array_init(|_| AtomicU8::new(0)),
+            children: array_init::array_init(|_| AtomicPtr::default()),
+        })) as *mut Header<V>,
+        KIND_N256 => Box::into_raw(Box::new(N256 {
+            header,
+            count,
+            children: array_init::array_init(|_| AtomicPtr::default()),
+        })) as *mut Header<V>,
+        _ => unreachable!(),
+    }
+}
+
+/// Frees a single node. The children of an inner node are not freed.
+///
+/// # Safety
+/// `node` must have come from `new_leaf` or `new_inner` and no thread may read it
+/// any more.
+unsafe fn free_node<V>(node: *mut Header<V>) {
+    match (*node).kind {
+        KIND_LEAF => drop(Box::from_raw(node as *mut Leaf<V>)),
+        KIND_N4 => drop(Box::from_raw(node as *mut N4<V>)),
+        KIND_N16 => drop(Box::from_raw(node as *mut N16<V>)),
+        KIND_N48 => drop(Box::from_raw(node as *mut N48<V>)),
+        KIND_N256 => drop(Box::from_raw(node as *mut N256<V>)),
+        _ => unreachable!(),
+    }
+}
+
+/// Frees a node and everything below it.
+///
+/// # Safety
+/// Same as `free_node`, for the whole subtree.
+unsafe fn free_tree<V>(node: *mut Header<V>) {
+    if !(*node).is_leaf() {
+        let leaf = (*node).leaf.load(Ordering::Relaxed);
+        if !leaf.is_null() {
+            free_node(leaf);
+        }
+        for_each_child(node, |_, child| free_tree(child));
+    }
+    free_node(node);
+}
+
+unsafe fn as_leaf<'a, V>(node: *mut Header<V>) -> &'a Leaf<V> {
+    debug_assert!((*node).is_leaf());
+    &*(node as *const Leaf<V>)
+}
+
+fn child_count<V>(node: *mut Header<V>) -> usize {
+    // every inner node keeps its count right after the header.
+    let count = unsafe {
+        match (*node).kind {
+            KIND_N4 => &(*(node as *const N4<V>)).count,
+            KIND_N16 => &(*(node as *const N16<V>)).count,
+            KIND_N48 => &(*(node as *const N48<V>)).count,
+            KIND_N256 => &(*(node as *const N256<V>)).count,
+            _ => unreachable!(),
+        }
+    };
+    count.load(Ordering::Acquire) as usize
+}
+
+fn capacity(kind: u8) -> usize {
+    match kind {
+        KIND_N4 => 4,
+        KIND_N16 => 16,
+        KIND_N48 => 48,
+        KIND_N256 => 256,
+        _ => unreachable!(),
+    }
+}
+
+/// The node type to shrink to once a node has `count` children left, if any.
+fn shrink_kind(kind: u8, count: usize) -> Option<u8> {
+    match kind {
+        KIND_N16 if count <= 3 => Some(KIND_N4),
+        KIND_N48 if count <= 12 => Some(KIND_N16),
+        KIND_N256 if count <= 37 => Some(KIND_N48),
+        _ => None,
+    }
+}
+
+fn find_child<V>(node: *mut Header<V>, byte: u8) -> *mut Header<V> {
+    unsafe {
+        match (*node).kind {
+            KIND_N4 => {
+                let n = &*(node as *const N4<V>);
+                find_in_keys(&n.count, &n.keys, &n.children, byte)
+            }
+            KIND_N16 => {
+                let n = &*(node as *const N16<V>);
+                find_in_keys(&n.count, &n.keys, &n.children, byte)
+            }
+            KIND_N48 => {
+                let n = &*(node as *const N48<V>);
+                match n.index[byte as usize].load(Ordering::Acquire) {
+                    0 => ptr::null_mut(),
+                    i => n.children[(i - 1) as usize].load(Ordering::Acquire),
+                }
+            }
+            KIND_N256 => {
+                let n = &*(node as *const N256<V>);
+                n.children[byte as usize].load(Ordering::Acquire)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn find_in_keys<V>(
+    count: &AtomicU16,
+    keys: &[AtomicU8],
+    children: &[AtomicPtr<Header<V>>],
+    byte: u8,
+) -> *mut Header<V> {
+    // a reader may see a count that is being changed, the version check catches it.
+    let count = (count.load(Ordering::Acquire) as usize).min(keys.len());
+    for i in 0..count {
+        if keys[i].load(Ordering::Acquire) == byte {
+            return children[i].load(Ordering::Acquire);
+        }
+    }
+    ptr::null_mut()
+}
+
+fn position_in_keys(count: &AtomicU16, keys: &[AtomicU8], byte: u8) -> Option<usize> {
+    let count = count.load(Ordering::Relaxed) as usize;
+    (0..count).find(|i| keys[*i].load(Ordering::Relaxed) == byte)
+}
+
+/// Calls `f` with every child of a locked or unreachable inner node.
+fn for_each_child<V>(node: *mut Header<V>, mut f: impl FnMut(u8, *mut Header<V>)) {
+    unsafe {
+        match (*node).kind {
+            KIND_N4 => {
+                let n = &*(node as *const N4<V>);
+                for i in 0..n.count.load(Ordering::Acquire) as usize {
+                    f(
+                        n.keys[i].load(Ordering::Acquire),
+                        n.children[i].load(Ordering::Acquire),
+                    );
+                }
+            }
+            KIND_N16 => {
+                let n = &*(node as *const N16<V>);
+                for i in 0..n.count.load(Ordering::Acquire) as usize {
+                    f(
+                        n.keys[i].load(Ordering::Acquire),
+                        n.children[i].load(Ordering::Acquire),
+                    );
+                }
+            }
+            KIND_N48 => {
+                let n = &*(node as *const N48<V>);
+                for byte in 0..256 {
+                    let i = n.index[byte].load(Ordering::Acquire);
+                    if i != 0 {
+                        f(
+                            byte as u8,
+                            n.children[(i - 1) as usize].load(Ordering::Acquire),
+                        );
+                    }
+                }
+            }
+            KIND_N256 => {
+                let n = &*(node as *const N256<V>);
+                for byte in 0..256 {
+                    let child = n.children[byte].load(Ordering::Acquire);
+                    if !child.is_null() {
+                        f(byte as u8, child);
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Adds a child to a write locked inner node that has room for it.
+fn add_child<V>(node: *mut Header<V>, byte: u8, child: *mut Header<V>) {
+    unsafe {
+        match (*node).kind {
+            KIND_N4 => {
+                let n = &*(node as *const N4<V>);
+                add_to_keys(&n.count, &n.keys, &n.children, byte, child)
+            }
+            KIND_N16 => {
+                let n = &*(node as *const N16<V>);
+                add_to_keys(&n.count, &n.keys, &n.children, byte, child)
+            }
+            KIND_N48 => {
+                let n = &*(node as *const N48<V>);
+                let slot = n
+                    .children
+                    .iter()
+                    .position(|c| c.load(Ordering::Relaxed).is_null())
+                    .unwrap();
+                n.children[slot].store(child, Ordering::Release);
+                n.index[byte as usize].store((slot + 1) as u8, Ordering::Release);
+                n.count.fetch_add(1, Ordering::Release);
+            }
+            KIND_N256 => {
+                let n = &*(node as *const N256<V>);
+                n.children[byte as usize].store(child, Ordering::Release);
+                n.count.fetch_add(1, Ordering::Release);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn add_to_keys<V>(
+    count: &AtomicU16,
+    keys: &[AtomicU8],
+    children: &[AtomicPtr<Header<V>>],
+    byte: u8,
+    child: *mut Header<V>,
+) {
+    let i = count.load(Ordering::Relaxed) as usize;
+    debug_assert!(i < keys.len());
+    children[i].store(child, Ordering::Release);
+    keys[i].store(byte, Ordering::Release);
+    count.store((i + 1) as u16, Ordering::Release);
+}
+
+/// Points an existing child of a write locked inner node somewhere else.
+fn change_child<V>(node: *mut Header<V>, byte: u8, child: *mut Header<V>) {
+    unsafe {
+        let slot = match (*node).kind {
+            KIND_N4 => {
+                let n = &*(node as *const N4<V>);
+                &n.children[position_in_keys(&n.count, &n.keys, byte).unwrap()]
+            }
+            KIND_N16 => {
+                let n = &*(node as *const N16<V>);
+                &n.children[position_in_keys(&n.count, &n.keys, byte).unwrap()]
+            }
+            KIND_N48 => {
+                let n = &*(node as *const N48<V>);
+                &n.children[(n.index[byte as usize].load(Ordering::Relaxed) - 1) as usize]
+            }
+            KIND_N256 => &(*(node as *const N256<V>)).children[byte as usize],
+            _ => unreachable!(),
+        };
+        slot.store(child, Ordering::Release);
+    }
+}
+
+/// Removes an existing child from a write locked inner node.
+fn remove_child<V>(node: *mut Header<V>, byte: u8) {
+    unsafe {
+        match (*node).kind {
+            KIND_N4 => {
+                let n = &*(node as *const N4<V>);
+                remove_from_keys(&n.count, &n.keys, &n.children, byte)
+            }
+            KIND_N16 => {
+                let n = &*(node as *const N16<V>);
+                remove_from_keys(&n.count, &n.keys, &n.children, byte)
+            }
+            KIND_N48 => {
+                let n = &*(node as *const N48<V>);
+                let i = n.index[byte as usize].load(Ordering::Relaxed);
+                n.index[byte as usize].store(0, Ordering::Release);
+                n.children[(i - 1) as usize].store(ptr::null_mut(), Ordering::Release);
+                n.count.fetch_sub(1, Ordering::Release);
+            }
+            KIND_N256 => {
+                let n = &*(node as *const N256<V>);
+                n.children[byte as usize].store(ptr::null_mut(), Ordering::Release);
+                n.count.fetch_sub(1, Ordering::Release);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn remove_from_keys<V>(
+    count: &AtomicU16,
+    keys: &[AtomicU8],
+    children: &[AtomicPtr<Header<V>>],
+    byte: u8,
+) {
+    // the last child moves into the hole, the order of the keys does not matter.
+    let i = position_in_keys(count, keys, byte).unwrap();
+    let last = count.load(Ordering::Relaxed) as usize - 1;
+    keys[i].store(keys[last].load(Ordering::Relaxed), Ordering::Release);
+    children[i].store(children[last].load(Ordering::Relaxed), Ordering::Release);
+    count.store(last as u16, Ordering::Release);
+}
+
+/// Copies a locked inner node into a new node of type `kind` behind `prefix`,
+/// leaving out the child for `skip`.
+fn copy_node<V>(node: *mut Header<V>, kind: u8, prefix: &[u8], skip: Option<u8>) -> *mut Header<V> {
+    let leaf = unsafe { (*node).leaf.load(Ordering::Acquire) };
+    let copy = new_inner(kind, prefix, leaf);
+    for_each_child(node, |byte, child| {
+        if Some(byte) != skip {
+            add_child(copy, byte, child);
+        }
+    });
+    copy
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+/// Puts `leaf` into a new inner node at the position of its key, given that
+/// `depth` bytes of the key lead to the node.
+fn place_leaf<V>(inner: *mut Header<V>, leaf_node: *mut Header<V>, depth: usize) {
+    let key = unsafe { &as_leaf(leaf_node).key };
+    match key.get(depth) {
+        Some(byte) => add_child(inner, *byte, leaf_node),
+        None => unsafe { (*inner).leaf.store(leaf_node, Ordering::Release) },
+    }
+}
+
+/// A tree of byte keys that any number of threads can read and write at once.
+///
+/// Values are handed out by cloning them, or to a closure with `get_with`, since
+/// a reference could outlive the value when another thread replaces it.
+///
+/// ```
+/// use std::sync::Arc;
+/// let tree = Arc::new(artoo::ConcurrentTree::new());
+/// let handles: Vec<_> = (0..4u32)
+///     .map(|t| {
+///         let tree = Arc::clone(&tree);
+///         std::thread::spawn(move || {
+///             for i in 0..1_000u32 {
+///                 tree.insert(&(t * 1_000 + i).to_be_bytes(), i);
+///             }
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert_eq!(tree.len(), 4_000);
+/// assert_eq!(tree.get(&2_500u32.to_be_bytes()), Some(500));
+/// ```
+pub struct ConcurrentTree<V> {
+    /// A Node256 that is never replaced, so every other node has a parent to lock.
+    root: *mut Header<V>,
+    count: AtomicUsize,
+    collector: Collector<V>,
+}
+
+unsafe impl<V: Send + Sync> Send for ConcurrentTree<V> {}
+unsafe impl<V: Send + Sync> Sync for ConcurrentTree<V> {}
+
+impl<V> ConcurrentTree<V> {
+    pub fn new() -> ConcurrentTree<V> {
+        ConcurrentTree {
+            root: new_inner(KIND_N256, &[], ptr::null_mut()),
+            count: AtomicUsize::new(0),
+            collector: Collector::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.get_with(key, |_| ()).is_some()
+    }
+
+    /// Calls `f` with the value of `key`, if there is one.
+    pub fn get_with<R>(&self, key: &[u8], f: impl FnOnce(&V) -> R) -> Option<R> {
+        let _guard = self.collector.pin();
+        let leaf = loop {
+            if let Ok(leaf) = self.find(key) {
+                break leaf;
+            }
+        };
+        // the leaf itself never changes and the guard keeps it alive.
+        match leaf.is_null() {
+            true => None,
+            false => Some(f(unsafe { &as_leaf(leaf).val })),
+        }
+    }
+
+    fn find(&self, key: &[u8]) -> Result<*mut Header<V>, Restart> {
+        let mut node = self.root;
+        let mut version = unsafe { (*node).read_lock()? };
+        let mut depth = 0;
+        loop {
+            let header = unsafe { &*node };
+            if !key[depth..].starts_with(&header.prefix) {
+                header.check(version)?;
+                return Ok(ptr::null_mut());
+            }
+            depth += header.prefix.len();
+            if depth == key.len() {
+                let leaf = header.leaf.load(Ordering::Acquire);
+                header.check(version)?;
+                return Ok(leaf);
+            }
+            let child = find_child(node, key[depth]);
+            header.check(version)?;
+            if child.is_null() {
+                return Ok(ptr::null_mut());
+            }
+            if unsafe { (*child).is_leaf() } {
+                return match unsafe { &as_leaf(child).key[..] } == key {
+                    true => Ok(child),
+                    false => Ok(ptr::null_mut()),
+                };
+            }
+            let child_version = unsafe { (*child).read_lock()? };
+            header.check(version)?;
+            node = child;
+            version = child_version;
+            depth += 1;
+        }
+    }
+
+    /// Replaces the leaf for `key` with `new`, returning the leaf it replaced or
+    /// null when the key is new.
+    fn try_insert(&self, key: &[u8], new: *mut Header<V>) -> Result<*mut Header<V>, Restart> {
+        let mut parent: *mut Header<V> = ptr::null_mut();
+        let mut parent_version = 0;
+        let mut parent_byte = 0;
+        let mut node = self.root;
+        let mut version = unsafe { (*node).read_lock()? };
+        let mut depth = 0;
+        loop {
+            let header = unsafe { &*node };
+            let common = common_prefix_len(&header.prefix, &key[depth..]);
+            if common < header.prefix.len() {
+                // the key leaves the prefix partway. A Node4 takes the place of
+                // the node and holds a copy of it with the rest of the prefix.
+                let parent_header = unsafe { &*parent };
+                parent_header.upgrade(parent_version)?;
+                if header.upgrade(version).is_err() {
+                    parent_header.unlock();
+                    return Err(Restart);
+                }
+                let split = new_inner(KIND_N4, &header.prefix[..common], ptr::null_mut());
+                let copy = copy_node(node, header.kind, &header.prefix[common + 1..], None);
+                add_child(split, header.prefix[common], copy);
+                place_leaf(split, new, depth + common);
+                change_child(parent, parent_byte, split);
+                parent_header.unlock();
+                header.unlock_obsolete();
+                self.collector.retire(node);
+                return Ok(ptr::null_mut());
+            }
+            depth += header.prefix.len();
+            if depth == key.len() {
+                header.upgrade(version)?;
+                let old = header.leaf.swap(new, Ordering::AcqRel);
+                header.unlock();
+                return Ok(old);
+            }
+            let byte = key[depth];
+            let child = find_child(node, byte);
+            header.check(version)?;
+            if child.is_null() {
+                if child_count(node) < capacity(header.kind) {
+                    header.upgrade(version)?;
+                    add_child(node, byte, new);
+                    header.unlock();
+                    return Ok(ptr::null_mut());
+                }
+                // a full node is replaced by a copy one size up. The root is a
+                // Node256 and never full, so there is always a parent here.
+                let parent_header = unsafe { &*parent };
+                parent_header.upgrade(parent_version)?;
+                if header.upgrade(version).is_err() {
+                    parent_header.unlock();
+                    return Err(Restart);
+                }
+                let grown = copy_node(node, header.kind + 1, &header.prefix, None);
+                add_child(grown, byte, new);
+                change_child(parent, parent_byte, grown);
+                parent_header.unlock();
+                header.unlock_obsolete();
+                self.collector.retire(node);
+                return Ok(ptr::null_mut());
+            }
+            if unsafe { (*child).is_leaf() } {
+                let existing = unsafe { &as_leaf(child).key };
+                header.upgrade(version)?;
+                if &existing[..] == key {
+                    change_child(node, byte, new);
+                    header.unlock();
+                    return Ok(child);
+                }
+                // lazy expansion: a second key below this byte turns the leaf
+                // into a Node4 behind the prefix both keys share.
+                let depth = depth + 1;
+                let common = common_prefix_len(&existing[depth..], &key[depth..]);
+                let inner = new_inner(KIND_N4, &key[depth..depth + common], ptr::null_mut());
+                place_leaf(inner, child, depth + common);
+                place_leaf(inner, new, depth + common);
+                change_child(node, byte, inner);
+                header.unlock();
+                return Ok(ptr::null_mut());
+            }
+            let child_version = unsafe { (*child).read_lock()? };
+            header.check(version)?;
+            parent = node;
+            parent_version = version;
+            parent_byte = byte;
+            node = child;
+            version = child_version;
+            depth += 1;
+        }
+    }
+
+    /// Unlinks the leaf for `key`, returning it or null when the key is missing.
+    fn try_remove(&self, key: &[u8]) -> Result<*mut Header<V>, Restart> {
+        let mut parent: *mut Header<V> = ptr::null_mut();
+        let mut parent_version = 0;
+        let mut parent_byte = 0;
+        let mut node = self.root;
+        let mut version = unsafe { (*node).read_lock()? };
+        let mut depth = 0;
+        loop {
+            let header = unsafe { &*node };
+            if !key[depth..].starts_with(&header.prefix) {
+                header.check(version)?;
+                return Ok(ptr::null_mut());
+            }
+            depth += header.prefix.len();
+            if depth == key.len() {
+                let old = header.leaf.load(Ordering::Acquire);
+                header.check(version)?;
+                if old.is_null() {
+                    return Ok(ptr::null_mut());
+                }
+                header.upgrade(version)?;
+                header.leaf.store(ptr::null_mut(), Ordering::Release);
+                header.unlock();
+                return Ok(old);
+            }
+            let byte = key[depth];
+            let child = find_child(node, byte);
+            header.check(version)?;
+            if child.is_null() {
+                return Ok(ptr::null_mut());
+            }
+            if unsafe { (*child).is_leaf() } {
+                if unsafe { &as_leaf(child).key[..] } != key {
+                    return Ok(ptr::null_mut());
+                }
+                let remaining = child_count(node) - 1;
+                let has_leaf = !header.leaf.load(Ordering::Acquire).is_null();
+                header.check(version)?;
+                let shrink = shrink_kind(header.kind, remaining);
+                if parent.is_null() || (shrink.is_none() && (remaining > 0 || has_leaf)) {
+                    header.upgrade(version)?;
+                    remove_child(node, byte);
+                    header.unlock();
+                    return Ok(child);
+                }
+                // the node is replaced in its parent by a smaller copy, or dropped
+                // when nothing is left in it.
+                let parent_header = unsafe { &*parent };
+                parent_header.upgrade(parent_version)?;
+                if header.upgrade(version).is_err() {
+                    parent_header.unlock();
+                    return Err(Restart);
+                }
+                if remaining == 0 && !has_leaf {
+                    remove_child(parent, parent_byte);
+                } else {
+                    let kind = shrink.unwrap();
+                    let smaller = copy_node(node, kind, &header.prefix, Some(byte));
+                    change_child(parent, parent_byte, smaller);
+                }
+                parent_header.unlock();
+                header.unlock_obsolete();
+                self.collector.retire(node);
+                return Ok(child);
+            }
+            let child_version = unsafe { (*child).read_lock()? };
+            header.check(version)?;
+            parent = node;
+            parent_version = version;
+            parent_byte = byte;
+            node = child;
+            version = child_version;
+            depth += 1;
+        }
+    }
+
+    /// Finds the first inner node on the path of `key` that holds fewer than two
+    /// entries and replaces it in its parent, returning whether there was one.
+    ///
+    /// An empty node is unlinked, a node with only its leaf is replaced by the leaf
+    /// and a node with a single child and no leaf is merged into the child. Each
+    /// change can leave the parent with fewer entries, so `remove` calls this until
+    /// it returns false and the cleanup cascades up to the root.
+    fn try_compact(&self, key: &[u8]) -> Result<bool, Restart> {
+        let mut parent = self.root;
+        let mut parent_version = unsafe { (*parent).read_lock()? };
+        let mut depth = 0;
+        while depth < key.len() {
+            let parent_header = unsafe { &*parent };
+            let byte = key[depth];
+            let node = find_child(parent, byte);
+            parent_header.check(parent_version)?;
+            if node.is_null() || unsafe { (*node).is_leaf() } {
+                return Ok(false);
+            }
+            let header = unsafe { &*node };
+            let version = header.read_lock()?;
+            parent_header.check(parent_version)?;
+            let count = child_count(node);
+            let leaf = header.leaf.load(Ordering::Acquire);
+            header.check(version)?;
+            if count + !leaf.is_null() as usize >= 2 {
+                if !key[depth + 1..].starts_with(&header.prefix) {
+                    return Ok(false);
+                }
+                parent = node;
+                parent_version = version;
+                depth += 1 + header.prefix.len();
+                continue;
+            }
+            parent_header.upgrade(parent_version)?;
+            if header.upgrade(version).is_err() {
+                parent_header.unlock();
+                return Err(Restart);
+            }
+            match (count, leaf.is_null()) {
+                (0, true) => remove_child(parent, byte),
+                (0, false) => change_child(parent, byte, leaf),
+                _ => {
+                    let mut only = (0, ptr::null_mut());
+                    for_each_child(node, |byte, child| only = (byte, child));
+                    let (child_byte, child) = only;
+                    if unsafe { (*child).is_leaf() } {
+                        change_child(parent, byte, child);
+                    } else {
+                        // the child's prefix cannot change, so a copy of it takes
+                        // the place of both with the two prefixes joined.
+                        let child_header = unsafe { &*child };
+                        let locked = child_header
+                            .read_lock()
+                            .and_then(|version| child_header.upgrade(version));
+                        if locked.is_err() {
+                            header.unlock();
+                            parent_header.unlock();
+                            return Err(Restart);
+                        }
+                        let mut prefix = header.prefix.to_vec();
+                        prefix.push(child_byte);
+                        prefix.extend_from_slice(&child_header.prefix);
+                        let merged = copy_node(child, child_header.kind, &prefix, None);
+                        change_child(parent, byte, merged);
+                        child_header.unlock_obsolete();
+                        self.collector.retire(child);
+                    }
+                }
+            }
+            parent_header.unlock();
+            header.unlock_obsolete();
+            self.collector.retire(node);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+impl<V: Clone> ConcurrentTree<V> {
+    pub fn get(&self, key: &[u8]) -> Option<V> {
+        self.get_with(key, V::clone)
+    }
+
+    /// Inserts a value under `key`, returning the previous value if there was one.
+    pub fn insert(&self, key: &[u8], val: V) -> Option<V> {
+        let _guard = self.collector.pin();
+        let new = new_leaf(key, val);
+        let old = loop {
+            if let Ok(old) = self.try_insert(key, new) {
+                break old;
+            }
+        };
+        if old.is_null() {
+            self.count.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        let val = unsafe { as_leaf(old).val.clone() };
+        self.collector.retire(old);
+        Some(val)
+    }
+
+    /// Removes `key` from the tree, returning its value if it was present.
+    pub fn remove(&self, key: &[u8]) -> Option<V> {
+        let _guard = self.collector.pin();
+        let old = loop {
+            if let Ok(old) = self.try_remove(key) {
+                break old;
+            }
+        };
+        if old.is_null() {
+            return None;
+        }
+        while !matches!(self.try_compact(key), Ok(false)) {}
+        self.count.fetch_sub(1, Ordering::Relaxed);
+        let val = unsafe { as_leaf(old).val.clone() };
+        self.collector.retire(old);
+        Some(val)
+    }
+}
+
+impl<V> Default for ConcurrentTree<V> {
+    fn default() -> Self {
+        ConcurrentTree::new()
+    }
+}
+
+impl<V> Drop for ConcurrentTree<V> {
+    fn drop(&mut self) {
+        // no other thread can hold a reference any more.
+        unsafe { free_tree(self.root) };
+    }
+}
+
+/// Epoch based reclamation for the nodes of one `ConcurrentTree`.
+///
+/// A thread pins the current epoch while it works on the tree. A retired node is
+/// tagged with the epoch it was unlinked in and freed once the global epoch is two
+/// past it, because the epoch only advances when every pinned thread has caught up
+/// with it, so no thread can still hold a pointer from before the unlink.
+///
+/// Each thread gathers its retired nodes in the bag of its participant and only
+/// hands a full bag over to `garbage`, where they are freed.
+struct Collector<V> {
+    id: usize,
+    epoch: AtomicU64,
+    participants: Mutex<Vec<Arc<Participant>>>,
+    garbage: Mutex<Vec<(u64, Retired<V>)>>,
+}
+
+struct Retired<V>(*mut Header<V>);
+
+/// The epoch a thread has pinned, shifted left by one with the low bit set while
+/// it is pinned.
+struct Participant {
+    state: AtomicU64,
+    /// Only touched by the owning thread, so nested pins keep the outer epoch.
+    nesting: AtomicUsize,
+    bag: Mutex<Bag>,
+}
+
+/// The nodes a thread retired, with their epochs. A participant belongs to a single
+/// collector, so they are all nodes of its value type.
+struct Bag(Vec<(u64, *mut ())>);
+
+// the nodes are only freed by their collector, which is shared like its tree.
+unsafe impl Send for Bag {}
+
+static NEXT_COLLECTOR_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static PARTICIPANTS: RefCell<HashMap<usize, Arc<Participant>>> = RefCell::new(HashMap::new());
+}
+
+/// How many nodes a thread retires between attempts to advance the epoch.
+const COLLECT_EVERY: usize = 64;
+
+impl<V> Collector<V> {
+    fn new() -> Collector<V> {
+        Collector {
+            id: NEXT_COLLECTOR_ID.fetch_add(1, Ordering::Relaxed),
+            epoch: AtomicU64::new(0),
+            participants: Mutex::new(Vec::new()),
+            garbage: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn participant(&self) -> Arc<Participant> {
+        PARTICIPANTS.with(|participants| {
+            let mut participants = participants.borrow_mut();
+            if let Some(participant) = participants.get(&self.id) {
+                return Arc::clone(participant);
+            }
+            // forget the collectors of trees that have been dropped.
+            participants.retain(|_, participant| Arc::strong_count(participant) > 1);
+            let participant = Arc::new(Participant {
+                state: AtomicU64::new(0),
+                nesting: AtomicUsize::new(0),
+                bag: Mutex::new(Bag(Vec::new())),
+            });
+            self.participants
+                .lock()
+                .unwrap()
+                .push(Arc::clone(&participant));
+            participants.insert(self.id, Arc::clone(&participant));
+            participant
+        })
+    }
+
+    fn pin(&self) -> Guard {
+        let participant = self.participant();
+        if participant.nesting.fetch_add(1, Ordering::Relaxed) == 0 {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            participant.state.store(epoch << 1 | 1, Ordering::SeqCst);
+            fence(Ordering::SeqCst);
+        }
+        Guard { participant }
+    }
+
+    fn retire(&self, node: *mut Header<V>) {
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        let participant = self.participant();
+        let mut bag = participant.bag.lock().unwrap();
+        bag.0.push((epoch, node.cast()));
+        if bag.0.len() < COLLECT_EVERY {
+            return;
+        }
+        let full = std::mem::take(&mut bag.0);
+        drop(bag);
+        let mut garbage = self.garbage.lock().unwrap();
+        garbage.extend(Self::unbag(full));
+        self.try_advance(&mut garbage);
+        let safe = self.epoch.load(Ordering::SeqCst);
+        garbage.retain(|(retired, node)| {
+            if retired + 2 > safe {
+                return true;
+            }
+            unsafe { free_node(node.0) };
+            false
+        });
+    }
+
+    fn unbag(bag: Vec<(u64, *mut ())>) -> impl Iterator<Item = (u64, Retired<V>)> {
+        bag.into_iter()
+            .map(|(epoch, node)| (epoch, Retired(node.cast())))
+    }
+
+    fn try_advance(&self, garbage: &mut Vec<(u64, Retired<V>)>) {
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        let mut participants = self.participants.lock().unwrap();
+        // a participant only this collector still holds belongs to a thread that
+        // exited, so its bag is taken over.
+        participants.retain(|participant| {
+            if Arc::strong_count(participant) > 1 {
+                return true;
+            }
+            garbage.extend(Self::unbag(std::mem::take(
+                &mut participant.bag.lock().unwrap().0,
+            )));
+            false
+        });
+        for participant in participants.iter() {
+            let state = participant.state.load(Ordering::SeqCst);
+            if state & 1 == 1 && state >> 1 != epoch {
+                return;
+            }
+        }
+        let _ = self
+            .epoch
+            .compare_exchange(epoch, epoch + 1, Ordering::SeqCst, Ordering::SeqCst);
+    }
+}
+
+impl<V> Drop for Collector<V> {
+    fn drop(&mut self) {
+        let garbage = self.garbage.get_mut().unwrap();
+        for participant in self.participants.get_mut().unwrap().iter() {
+            garbage.extend(Self::unbag(std::mem::take(
+                &mut participant.bag.lock().unwrap().0,
+            )));
+        }
+        for (_, node) in garbage.drain(..) {
+            unsafe { free_node(node.0) };
+        }
+    }
+}
+
+struct Guard {
+    participant: Arc<Participant>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if self.participant.nesting.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.participant.state.store(0, Ordering::Release);
+        }
+    }
+}
+
+#[cfg(test)]
+fn stress_key(i: u32) -> Vec<u8> {
+    // a mix of shared prefixes, prefix keys and long tails so every node type,
+    // prefix split and lazy expansion is reached.
+    match i % 4 {
+        0 => i.to_be_bytes().to_vec(),
+        1 => (i >> 4).to_be_bytes()[1..].to_vec(),
+        2 => format!("user/{}/profile", i % 97).into_bytes(),
+        _ => format!("user/{}", i % 211).into_bytes(),
+    }
+}
+
+/// Asserts that every inner node below the root holds at least two entries.
+#[cfg(test)]
+fn assert_compact<V>(node: *mut Header<V>) {
+    for_each_child(node, |_, child| {
+        if unsafe { !(*child).is_leaf() } {
+            let leaf = unsafe { (*child).leaf.load(Ordering::Relaxed) };
+            assert!(child_count(child) + !leaf.is_null() as usize >= 2);
+            assert_compact(child);
+        }
+    });
+}
+
+#[test]
+fn test_concurrent_tree_single_thread_matches_btree_map() {
+    use std::collections::BTreeMap;
+
+    let tree = ConcurrentTree::new();
+    let mut btree = BTreeMap::new();
+    let mut state = 0x2545_f491u32;
+    for _ in 0..50_000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let key = stress_key(state % 5_000);
+        match state % 3 {
+            0 => assert_eq!(tree.remove(&key), btree.remove(&key)),
+            _ => assert_eq!(tree.insert(&key, state), btree.insert(key.clone(), state)),
+        }
+        assert_eq!(tree.len(), btree.len());
+    }
+    for i in 0..5_000 {
+        let key = stress_key(i);
+        assert_eq!(tree.get(&key), btree.get(&key).copied());
+    }
+    assert_compact(tree.root);
+    assert!(!tree.contains_key(b"user/"));
+    assert_eq!(tree.get(b""), None);
+    tree.insert(b"", 1);
+    assert_eq!(tree.get(b""), Some(1));
+    assert_eq!(tree.remove(b""), Some(1));
+}
+
+#[test]
+fn test_concurrent_tree_stress_against_mutex_btree_map() {
+    use std::collections::BTreeMap;
+    use std::thread;
+
+    let threads = 8u32;
+    // a key made from numbers of different threads is shared by them, the rest
+    // are owned by one thread, so the result of each operation on them is known.
+    let mut owners = HashMap::<Vec<u8>, Option<u32>>::new();
+    for i in 0..4_000 {
+        let owner = owners.entry(stress_key(i)).or_insert(Some(i % threads));
+        if *owner != Some(i % threads) {
+            *owner = None;
+        }
+    }
+    let owners = Arc::new(owners);
+    let tree = Arc::new(ConcurrentTree::<u64>::new());
+    let oracle = Arc::new(Mutex::new(BTreeMap::<Vec<u8>, u64>::new()));
+    let handles: Vec<_> = (0..threads)
+        .map(|t| {
+            let tree = Arc::clone(&tree);
+            let oracle = Arc::clone(&oracle);
+            let owners = Arc::clone(&owners);
+            thread::spawn(move || {
+                let mut state = 0x9e37_79b9u32 ^ (t + 1).wrapping_mul(0x85eb_ca6b);
+                for n in 0..20_000u64 {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    let key = stress_key(state % 4_000);
+                    match owners[&key] {
+                        Some(owner) if owner == t => {}
+                        // the keys of other threads are only read.
+                        Some(_) => {
+                            tree.get(&key);
+                            continue;
+                        }
+                        // every thread writes the same value to a shared key.
+                        None => {
+                            let val = key.len() as u64;
+                            tree.insert(&key, val);
+                            oracle.lock().unwrap().insert(key, val);
+                            continue;
+                        }
+                    }
+                    let val = (t as u64) << 32 | n;
+                    match state % 4 {
+                        0 => {
+                            let removed = tree.remove(&key);
+                            assert_eq!(removed, oracle.lock().unwrap().remove(&key));
+                        }
+                        1 => {
+                            let found = tree.get(&key);
+                            assert_eq!(found, oracle.lock().unwrap().get(&key).copied());
+                        }
+                        _ => {
+                            let old = tree.insert(&key, val);
+                            assert_eq!(old, oracle.lock().unwrap().insert(key, val));
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let oracle = oracle.lock().unwrap();
+    assert_eq!(tree.len(), oracle.len());
+    for (key, val) in oracle.iter() {
+        assert_eq!(tree.get(key), Some(*val));
+    }
+}
+
+#[test]
+fn test_concurrent_tree_readers_see_stable_keys_while_writers_churn() {
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    let tree = Arc::new(ConcurrentTree::<u32>::new());
+    // stable keys live among the churned ones and are never written again.
+    for i in (0..10_000u32).step_by(10) {
+        tree.insert(&i.to_be_bytes(), i);
+    }
+    let done = Arc::new(AtomicBool::new(false));
+    let writers: Vec<_> = (0..4u32)
+        .map(|t| {
+            let tree = Arc::clone(&tree);
+            thread::spawn(move || {
+                for round in 0..5u32 {
+                    for i in (0..10_000u32).filter(|i| i % 10 != 0 && i % 4 == t) {
+                        match round % 2 {
+                            0 => tree.insert(&i.to_be_bytes(), i),
+                            _ => tree.remove(&i.to_be_bytes()),
+                        };
+                    }
+                }
+            })
+        })
+        .collect();
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let tree = Arc::clone(&tree);
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                let mut reads = 0u32;
+                while !done.load(Ordering::Relaxed) || reads < 10_000 {
+                    let i = (reads * 7919 % 1_000) * 10;
+                    assert_eq!(tree.get(&i.to_be_bytes()), Some(i));
+                    reads += 1;
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    done.store(true, Ordering::Relaxed);
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    // every writer finished on an insert round.
+    assert_eq!(tree.len(), 10_000);
+}
+
+#[test]
+fn test_concurrent_tree_churn_leaves_an_empty_root() {
+    let tree = Arc::new(ConcurrentTree::new());
+    let handles: Vec<_> = (0..4u32)
+        .map(|t| {
+            let tree = Arc::clone(&tree);
+            std::thread::spawn(move || {
+                // the threads share prefixes but each owns its keys.
+                let keys: Vec<Vec<u8>> = (0..2_000u32)
+                    .map(|i| {
+                        let mut key = stress_key(i);
+                        key.push(t as u8);
+                        key
+                    })
+                    .collect();
+                for round in 0..5u32 {
+                    for key in &keys {
+                        tree.insert(key, round);
+                    }
+                    for key in keys.iter().skip(round as usize % 2).step_by(2) {
+                        tree.remove(key);
+                    }
+                }
+                for key in &keys {
+                    tree.remove(key);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert!(tree.is_empty());
+    assert_eq!(child_count(tree.root), 0);
+    assert!(unsafe { (*tree.root).leaf.load(Ordering::Relaxed) }.is_null());
+
+    // removing part of the keys leaves no node with fewer than two entries.
+    for i in 0..4_000u32 {
+        tree.insert(&stress_key(i), i);
+    }
+    for i in (0..4_000u32).filter(|i| i % 3 != 0) {
+        tree.remove(&stress_key(i));
+    }
+    assert_compact(tree.root);
+}
+
+#[test]
+fn test_concurrent_tree_reclaims_retired_nodes() {
+    use std::sync::atomic::AtomicUsize as Counter;
+
+    static DROPS: Counter = Counter::new(0);
+
+    #[derive(Clone)]
+    struct Counted;
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let tree = ConcurrentTree::new();
+    for round in 0..10u32 {
+        for i in 0..1_000u32 {
+            tree.insert(&(i * round).to_be_bytes(), Counted);
+        }
+    }
+    // without other threads pinned the collector keeps up with the garbage.
+    assert!(tree.collector.garbage.lock().unwrap().len() < 2 * COLLECT_EVERY);
+    let live = tree.len();
+    drop(tree);
+    // every value inserted was dropped once, clones handed back included.
+    assert!(DROPS.load(Ordering::Relaxed) >= 10_000 + live);
+}
+
+#[test]
+fn test_collector_takes_over_the_bags_of_exited_threads() {
+    let tree = Arc::new(ConcurrentTree::new());
+    let writer = Arc::clone(&tree);
+    // joining waits for the thread local participant record to be dropped too.
+    std::thread::spawn(move || {
+        for i in 0..10u32 {
+            writer.insert(b"key", i);
+        }
+    })
+    .join()
+    .unwrap();
+    let bagged = |tree: &ConcurrentTree<u32>| -> Vec<usize> {
+        let participants = tree.collector.participants.lock().unwrap();
+        participants
+            .iter()
+            .map(|participant| participant.bag.lock().unwrap().0.len())
+            .collect()
+    };
+    // the overwritten leaves wait in the bag of the thread that retired them.
+    let left = bagged(&tree)[0];
+    assert!(left > 0);
+    assert!(tree.collector.garbage.lock().unwrap().is_empty());
+    // the first insert adds the key, each one after it retires the leaf it replaces.
+    for i in 0..=COLLECT_EVERY as u32 {
+        tree.insert(b"other", i);
+    }
+    // filling a bag collects, which also takes over the bag of the exited thread.
+    assert_eq!(bagged(&tree), vec![0]);
+    assert!(tree.collector.garbage.lock().unwrap().len() >= left);
+}
@@ -0,0 +1,900 @@
+use crate::iter::compare_sequence;
+use crate::{Key, Node, Seek, Tree};
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use std::ops::Bound;
+use std::ptr::NonNull;
+
+/// A node on the path of a cursor. `Cursor` holds shared references and
+/// `CursorMut` holds `NodeMut` pointers that it can write values through.
+trait NodeRef: Copy {
+    type Value;
+
+    /// A short-lived view of the node.
+    fn node(&self) -> &Node<Self::Value>;
+
+    /// The child at `byte`, which must exist, or the node of a Branch for `None`.
+    fn child(self, byte: Option<u8>) -> Self;
+}
+
+impl<V> NodeRef for &Node<V> {
+    type Value = V;
+
+    fn node(&self) -> &Node<V> {
+        self
+    }
+
+    fn child(self, byte: Option<u8>) -> Self {
+        match (self, byte) {
+            (Node::Branch(branch), None) => &branch.node,
+            (node, Some(byte)) => node.find_child(Seek::at(&[byte], 0)).unwrap(),
+            (_, None) => unreachable!(),
+        }
+    }
+}
+
+/// A node of the tree that a `CursorMut` borrows.
+///
+/// The aliasing rules a `CursorMut` keeps for these pointers:
+/// - They are only derived from its pointer to the tree through `&mut`
+///   references, so they may be written through.
+/// - While any of them is on the path, the cursor does not touch the tree
+///   otherwise. Every change to the tree clears the path first and takes a new
+///   root pointer after, so no pointer outlives a change to the node it points at.
+/// - Taking a child goes through `&mut` of its parent, which invalidates every
+///   pointer taken below that parent before. So the cursor keeps a single path of
+///   them, each taken from the one above it, and never steps a copy of it.
+/// - The references they hand out are tied to a borrow of the cursor, so none is
+///   alive when the cursor moves or changes the tree.
+struct NodeMut<V>(NonNull<Node<V>>);
+
+impl<V> Clone for NodeMut<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for NodeMut<V> {}
+
+impl<V> NodeRef for NodeMut<V> {
+    type Value = V;
+
+    fn node(&self) -> &Node<V> {
+        // SAFETY: the pointer is valid by the rules on `NodeMut` and nothing writes
+        // to the tree while the view is alive.
+        unsafe { self.0.as_ref() }
+    }
+
+    fn child(self, byte: Option<u8>) -> Self {
+        // SAFETY: as for `node`, and no other reference into the tree is alive.
+        let node = unsafe { &mut *self.0.as_ptr() };
+        let child = match (node, byte) {
+            (Node::Branch(branch), None) => &mut branch.node,
+            (node, Some(byte)) => node.find_child_mut(Seek::at(&[byte], 0)).unwrap(),
+            (_, None) => unreachable!(),
+        };
+        NodeMut(NonNull::from(child))
+    }
+}
+
+/// One step of the path from the root to the cursor.
+///
+/// `child` is the byte of the child the path continues into, which is also its
+/// index in a Node48 or Node256. It is `None` when the path stops at the node's
+/// own value. While seeking it may name a byte that has no child, which still
+/// orders the node's real children around the position.
+#[derive(Clone)]
+struct Frame<N> {
+    node: N,
+    depth: usize,
+    child: Option<u8>,
+}
+
+/// The position of a cursor: the nodes from the root down to the current value
+/// and the key that leads to it. An empty path is the unpositioned cursor.
+#[derive(Clone)]
+struct Path<N> {
+    frames: Vec<Frame<N>>,
+    key: Vec<u8>,
+}
+
+impl<N: NodeRef> Path<N> {
+    fn new() -> Path<N> {
+        Path {
+            frames: Vec::new(),
+            key: Vec::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.frames.clear();
+        self.key.clear();
+    }
+
+    fn push(&mut self, node: N, depth: usize, child: Option<u8>) {
+        self.frames.push(Frame { node, depth, child });
+    }
+
+    /// The node holding the current value.
+    fn top(&self) -> Option<N> {
+        let frame = self.frames.last()?;
+        frame.node.node().leaf().map(|_| frame.node)
+    }
+
+    /// Moves to the first value in the subtree of `node`.
+    fn first(&mut self, mut node: N, mut depth: usize) -> bool {
+        loop {
+            let byte = match node.node() {
+                Node::None => return false,
+                Node::Leaf(_) | Node::BoxNodeLeaf(_, _) => {
+                    // a node's own value sorts before everything below it.
+                    self.push(node, depth, None);
+                    return true;
+                }
+                Node::BoxNode(bn) => {
+                    let (byte, _) = bn.next_child(None).unwrap();
+                    self.push(node, depth, Some(byte));
+                    self.key.push(byte);
+                    depth += 1;
+                    Some(byte)
+                }
+                Node::Branch(branch) => {
+                    self.push(node, depth, Some(0));
+                    self.key.extend_from_slice(branch.sequence());
+                    depth += branch.sequence.len();
+                    None
+                }
+            };
+            node = node.child(byte);
+        }
+    }
+
+    /// Moves to the last value in the subtree of `node`.
+    fn last(&mut self, mut node: N, mut depth: usize) -> bool {
+        loop {
+            let byte = match node.node() {
+                Node::None => return false,
+                Node::Leaf(_) => {
+                    self.push(node, depth, None);
+                    return true;
+                }
+                Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => {
+                    let (byte, _) = bn.prev_child(None).unwrap();
+                    self.push(node, depth, Some(byte));
+                    self.key.push(byte);
+                    depth += 1;
+                    Some(byte)
+                }
+                Node::Branch(branch) => {
+                    self.push(node, depth, Some(0));
+                    self.key.extend_from_slice(branch.sequence());
+                    depth += branch.sequence.len();
+                    None
+                }
+            };
+            node = node.child(byte);
+        }
+    }
+
+    /// Moves to the first value after the top of the path, going up until a node
+    /// has a later child.
+    fn step_next(&mut self) -> bool {
+        while let Some(frame) = self.frames.pop() {
+            self.key.truncate(frame.depth);
+            let next = match frame.node.node() {
+                Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => bn.next_child(frame.child),
+                _ => None,
+            };
+            if let Some((byte, _)) = next {
+                self.push(frame.node, frame.depth, Some(byte));
+                self.key.push(byte);
+                return self.first(frame.node.child(Some(byte)), frame.depth + 1);
+            }
+        }
+        false
+    }
+
+    /// Moves to the last value before the top of the path.
+    fn step_prev(&mut self) -> bool {
+        while let Some(frame) = self.frames.pop() {
+            self.key.truncate(frame.depth);
+            let before = match frame.child {
+                // the node's own value comes before all of its children.
+                None => continue,
+                Some(byte) => byte,
+            };
+            let (prev, has_leaf) = match frame.node.node() {
+                Node::BoxNode(bn) => (bn.prev_child(Some(before)), false),
+                Node::BoxNodeLeaf(bn, _) => (bn.prev_child(Some(before)), true),
+                _ => continue,
+            };
+            if let Some((byte, _)) = prev {
+                self.push(frame.node, frame.depth, Some(byte));
+                self.key.push(byte);
+                return self.last(frame.node.child(Some(byte)), frame.depth + 1);
+            }
+            if has_leaf {
+                self.push(frame.node, frame.depth, None);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Moves to the first value at or after `target`.
+    ///
+    /// The descent follows `find_child` along the target. Where the tree leaves
+    /// the target the path is left just in front of the subtree that sorts after
+    /// it, so a single `step_next` finishes the seek.
+    fn seek_ge(&mut self, root: N, target: &[u8]) -> bool {
+        self.clear();
+        let mut node = root;
+        let mut depth = 0;
+        while depth < target.len() {
+            let byte = match node.node() {
+                Node::None => return false,
+                // the leaf's key is a strict prefix of the target and sorts before it.
+                Node::Leaf(_) => {
+                    self.push(node, depth, None);
+                    return self.step_next();
+                }
+                Node::Branch(branch) => {
+                    let sequence = branch.sequence();
+                    match compare_sequence(sequence, &target[depth..]) {
+                        Ordering::Equal => {
+                            self.push(node, depth, Some(0));
+                            self.key.extend_from_slice(sequence);
+                            depth += sequence.len();
+                            None
+                        }
+                        Ordering::Greater => return self.first(node, depth),
+                        Ordering::Less => {
+                            self.push(node, depth, Some(0));
+                            return self.step_next();
+                        }
+                    }
+                }
+                Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => {
+                    let seek = Seek::at(target, depth);
+                    self.push(node, depth, Some(seek.byte));
+                    if bn.find_child(seek).is_none() {
+                        return self.step_next();
+                    }
+                    self.key.push(seek.byte);
+                    depth += 1;
+                    Some(seek.byte)
+                }
+            };
+            node = node.child(byte);
+        }
+        // everything below `node` is at or after the target.
+        self.first(node, depth)
+    }
+
+    /// Moves to the last value at or before `target`.
+    fn seek_le(&mut self, root: N, target: &[u8]) -> bool {
+        self.clear();
+        let mut node = root;
+        let mut depth = 0;
+        while depth < target.len() {
+            let byte = match node.node() {
+                Node::None => return false,
+                Node::Leaf(_) => {
+                    self.push(node, depth, None);
+                    return true;
+                }
+                Node::Branch(branch) => {
+                    let sequence = branch.sequence();
+                    match compare_sequence(sequence, &target[depth..]) {
+                        Ordering::Equal => {
+                            self.push(node, depth, Some(0));
+                            self.key.extend_from_slice(sequence);
+                            depth += sequence.len();
+                            None
+                        }
+                        Ordering::Less => return self.last(node, depth),
+                        Ordering::Greater => {
+                            self.push(node, depth, None);
+                            return self.step_prev();
+                        }
+                    }
+                }
+                Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => {
+                    let seek = Seek::at(target, depth);
+                    self.push(node, depth, Some(seek.byte));
+                    if bn.find_child(seek).is_none() {
+                        return self.step_prev();
+                    }
+                    self.key.push(seek.byte);
+                    depth += 1;
+                    Some(seek.byte)
+                }
+            };
+            node = node.child(byte);
+        }
+        // only the node's own value can be at the target, its children are after it.
+        self.push(node, depth, None);
+        if node.node().leaf().is_some() {
+            return true;
+        }
+        self.step_prev()
+    }
+}
+
+/// A position in a `Tree` that can move in both directions and seek.
+///
+/// A cursor starts out unpositioned. Moving past either end also leaves it
+/// unpositioned, and from there `next` moves to the first entry and `prev` to the
+/// last one. Cursors from `Tree::lower_bound` and `Tree::upper_bound` do not wrap
+/// around like that: past the last entry `next` keeps returning `None` and `prev`
+/// moves back to the last entry, and the same holds before the first entry.
+///
+/// The cursor keeps the path from the root to its entry, so stepping only walks
+/// the nodes between two neighbouring entries.
+///
+/// ```
+/// let tree: artoo::Tree<u32> = [("apple", 1), ("banana", 2), ("cherry", 3)]
+///     .into_iter()
+///     .collect();
+/// let mut cursor = tree.cursor();
+/// assert!(cursor.seek_ge(b"b"));
+/// assert_eq!(cursor.key(), Some(&b"banana"[..]));
+/// assert_eq!(cursor.next(), Some((&b"cherry"[..], &3)));
+/// assert_eq!(cursor.next(), None);
+/// assert_eq!(cursor.next(), Some((&b"apple"[..], &1)));
+/// ```
+#[derive(Clone)]
+pub struct Cursor<'a, V> {
+    root: &'a Node<V>,
+    path: Path<&'a Node<V>>,
+    /// The end an unpositioned cursor is past, for a cursor that does not wrap
+    /// around. `None` for one that does.
+    past: Option<End>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum End {
+    Front,
+    Back,
+}
+
+impl<'a, V> Cursor<'a, V> {
+    pub fn key(&self) -> Option<&[u8]> {
+        self.path.top().map(|_| &self.path.key[..])
+    }
+
+    pub fn value(&self) -> Option<&'a V> {
+        self.path.top()?.leaf().map(|leaf| &leaf.val)
+    }
+
+    fn entry(&self) -> Option<(&[u8], &'a V)> {
+        Some((&self.path.key, self.value()?))
+    }
+
+    /// Moves to `key`, leaving the cursor unpositioned if it is missing.
+    pub fn seek(&mut self, key: &[u8]) -> bool {
+        if self.seek_ge(key) && self.path.key == key {
+            return true;
+        }
+        self.path.clear();
+        false
+    }
+
+    /// Moves to the first entry at or after `key`.
+    pub fn seek_ge(&mut self, key: &[u8]) -> bool {
+        let found = self.path.seek_ge(self.root, key);
+        self.fell_off(found, End::Back);
+        found
+    }
+
+    /// Moves to the last entry at or before `key`.
+    pub fn seek_le(&mut self, key: &[u8]) -> bool {
+        let found = self.path.seek_le(self.root, key);
+        self.fell_off(found, End::Front);
+        found
+    }
+
+    /// Moves to the next entry and returns it.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(&[u8], &'a V)> {
+        let moved = match self.path.frames.is_empty() {
+            true if self.past == Some(End::Back) => false,
+            true => self.path.first(self.root, 0),
+            false => self.path.step_next(),
+        };
+        self.fell_off(moved, End::Back);
+        moved.then(|| self.entry()).flatten()
+    }
+
+    /// Moves to the previous entry and returns it.
+    pub fn prev(&mut self) -> Option<(&[u8], &'a V)> {
+        let moved = match self.path.frames.is_empty() {
+            true if self.past == Some(End::Front) => false,
+            true => self.path.last(self.root, 0),
+            false => self.path.step_prev(),
+        };
+        self.fell_off(moved, End::Front);
+        moved.then(|| self.entry()).flatten()
+    }
+
+    /// Records the end that a cursor which does not wrap around has moved past.
+    fn fell_off(&mut self, moved: bool, end: End) {
+        if !moved && self.past.is_some() {
+            self.past = Some(end);
+        }
+    }
+}
+
+/// A cursor that can also change the tree at its position.
+///
+/// Values are read and written through the path, so `value_mut` keeps the
+/// position. `remove_current` and `insert_after` change the tree itself and seek
+/// back to the cursor's position afterwards.
+pub struct CursorMut<'a, V> {
+    /// The tree borrowed for `'a`. It is a pointer rather than a reference so that
+    /// moving the cursor does not invalidate the `NodeMut` pointers derived from it.
+    tree: NonNull<Tree<V>>,
+    root: NodeMut<V>,
+    path: Path<NodeMut<V>>,
+    _tree: PhantomData<&'a mut Tree<V>>,
+}
+
+impl<'a, V> CursorMut<'a, V> {
+    fn new(tree: &'a mut Tree<V>) -> CursorMut<'a, V> {
+        let tree = NonNull::from(tree);
+        CursorMut {
+            tree,
+            root: CursorMut::root_of(tree),
+            path: Path::new(),
+            _tree: PhantomData,
+        }
+    }
+
+    fn root_of(tree: NonNull<Tree<V>>) -> NodeMut<V> {
+        // SAFETY: the tree is borrowed mutably for as long as the cursor lives.
+        NodeMut(NonNull::from(unsafe { &mut (*tree.as_ptr()).root }))
+    }
+
+    /// The tree, to change it. The path must be empty, see `NodeMut`.
+    fn tree_mut(&mut self) -> &mut Tree<V> {
+        debug_assert!(self.path.frames.is_empty());
+        // SAFETY: the tree is borrowed mutably for as long as the cursor lives and
+        // no pointer into it is left on the path.
+        unsafe { self.tree.as_mut() }
+    }
+
+    /// Seeks back to `key` after a change to the tree, which leaves the cursor
+    /// unpositioned if nothing is at or after it.
+    fn reseek(&mut self, key: &[u8]) {
+        self.root = CursorMut::root_of(self.tree);
+        if !self.path.seek_ge(self.root, key) {
+            self.path.clear();
+        }
+    }
+
+    pub fn key(&self) -> Option<&[u8]> {
+        self.path.top().map(|_| &self.path.key[..])
+    }
+
+    pub fn value(&self) -> Option<&V> {
+        let frame = self.path.frames.last()?;
+        frame.node.node().leaf().map(|leaf| &leaf.val)
+    }
+
+    pub fn value_mut(&mut self) -> Option<&mut V> {
+        let top = self.path.top()?;
+        // SAFETY: `top` follows the rules on `NodeMut` and the returned borrow of the
+        // cursor keeps every other reference into the tree from being made.
+        let node = unsafe { &mut *top.0.as_ptr() };
+        node.leaf_mut().map(|leaf| &mut leaf.val)
+    }
+
+    fn entry(&self) -> Option<(&[u8], &V)> {
+        Some((&self.path.key, self.value()?))
+    }
+
+    pub fn seek(&mut self, key: &[u8]) -> bool {
+        if self.seek_ge(key) && self.path.key == key {
+            return true;
+        }
+        self.path.clear();
+        false
+    }
+
+    pub fn seek_ge(&mut self, key: &[u8]) -> bool {
+        self.path.seek_ge(self.root, key)
+    }
+
+    pub fn seek_le(&mut self, key: &[u8]) -> bool {
+        self.path.seek_le(self.root, key)
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(&[u8], &V)> {
+        let moved = match self.path.frames.is_empty() {
+            true => self.path.first(self.root, 0),
+            false => self.path.step_next(),
+        };
+        moved.then(|| self.entry()).flatten()
+    }
+
+    pub fn prev(&mut self) -> Option<(&[u8], &V)> {
+        let moved = match self.path.frames.is_empty() {
+            true => self.path.last(self.root, 0),
+            false => self.path.step_prev(),
+        };
+        moved.then(|| self.entry()).flatten()
+    }
+
+    /// Removes the current entry and moves to the one after it.
+    pub fn remove_current(&mut self) -> Option<(Vec<u8>, V)> {
+        let key = self.key()?.to_vec();
+        self.path.clear();
+        let val = self.tree_mut().remove(&key).unwrap();
+        self.reseek(&key);
+        Some((key, val))
+    }
+
+    /// Inserts an entry right after the current one without moving the cursor.
+    ///
+    /// An unpositioned cursor inserts in front of the first entry.
+    ///
+    /// # Panics
+    /// Panics if `key` does not sort between the current entry and the next one.
+    pub fn insert_after(&mut self, key: &[u8], val: V) {
+        let current = self.key().map(<[u8]>::to_vec);
+        // the path itself steps ahead, a copy of it would not keep its pointers.
+        let mut next = std::mem::replace(&mut self.path, Path::new());
+        let has_next = match &current {
+            None => next.first(self.root, 0),
+            Some(current) => {
+                assert!(key > &current[..], "key must sort after the cursor");
+                next.step_next()
+            }
+        };
+        if has_next {
+            assert!(key < &next.key[..], "key must sort before the next entry");
+        }
+        drop(next);
+        self.tree_mut().insert(key, val);
+        match current {
+            Some(current) => self.reseek(&current),
+            None => self.root = CursorMut::root_of(self.tree),
+        }
+    }
+}
+
+impl<V> Tree<V> {
+    /// An unpositioned cursor over the tree.
+    pub fn cursor(&self) -> Cursor<'_, V> {
+        Cursor {
+            root: &self.root,
+            path: Path::new(),
+            past: None,
+        }
+    }
+
+    /// An unpositioned cursor past `end` that does not wrap around.
+    fn bounded_cursor(&self, end: End) -> Cursor<'_, V> {
+        Cursor {
+            past: Some(end),
+            ..self.cursor()
+        }
+    }
+
+    /// An unpositioned cursor that can change the tree.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, V> {
+        CursorMut::new(self)
+    }
+
+    /// The entry with the largest key at or before `key`.
+    ///
+    /// The descent follows `key` and backs up to the nearest smaller sibling where
+    /// the tree leaves it, so only one path down and one back is visited.
+    ///
+    /// ```
+    /// let mut segments = artoo::Tree::new();
+    /// for start in [0u64, 4096, 10_000] {
+    ///     segments.insert(&start.to_be_bytes(), start);
+    /// }
+    /// let (_, start) = segments.floor(&5_000u64.to_be_bytes()).unwrap();
+    /// assert_eq!(*start, 4096);
+    /// assert_eq!(segments.ceiling(&10_001u64.to_be_bytes()), None);
+    /// ```
+    pub fn floor(&self, key: &[u8]) -> Option<(Key, &V)> {
+        let mut cursor = self.cursor();
+        cursor.seek_le(key);
+        cursor.entry().map(|(k, v)| (Key::from(k), v))
+    }
+
+    /// The entry with the smallest key at or after `key`.
+    pub fn ceiling(&self, key: &[u8]) -> Option<(Key, &V)> {
+        let mut cursor = self.cursor();
+        cursor.seek_ge(key);
+        cursor.entry().map(|(k, v)| (Key::from(k), v))
+    }
+
+    /// A cursor at the first entry above `bound`.
+    ///
+    /// When there is none the cursor is past the last entry, where `next` returns
+    /// `None` rather than wrapping around to the first entry and `prev` moves to the
+    /// last entry below the bound.
+    pub fn lower_bound(&self, bound: Bound<&[u8]>) -> Cursor<'_, V> {
+        let mut cursor = self.bounded_cursor(End::Front);
+        match bound {
+            Bound::Unbounded => {
+                cursor.next();
+            }
+            Bound::Included(key) => {
+                cursor.seek_ge(key);
+            }
+            Bound::Excluded(key) => {
+                if cursor.seek_ge(key) && cursor.key() == Some(key) {
+                    cursor.next();
+                }
+            }
+        }
+        cursor
+    }
+
+    /// A cursor at the last entry below `bound`.
+    ///
+    /// When there is none the cursor is before the first entry, where `prev` returns
+    /// `None` rather than wrapping around to the last entry and `next` moves to the
+    /// first entry above the bound.
+    pub fn upper_bound(&self, bound: Bound<&[u8]>) -> Cursor<'_, V> {
+        let mut cursor = self.bounded_cursor(End::Back);
+        match bound {
+            Bound::Unbounded => {
+                cursor.prev();
+            }
+            Bound::Included(key) => {
+                cursor.seek_le(key);
+            }
+            Bound::Excluded(key) => {
+                if cursor.seek_le(key) && cursor.key() == Some(key) {
+                    cursor.prev();
+                }
+            }
+        }
+        cursor
+    }
+}
+
+#[cfg(test)]
+fn cursor_test_tree() -> (Tree<usize>, Vec<Vec<u8>>) {
+    let mut keys: Vec<Vec<u8>> = vec![];
+    for i in 0..3_000u32 {
+        keys.push((i * 37).to_be_bytes().to_vec());
+    }
+    for word in [
+        "",
+        "a",
+        "ab",
+        "abc",
+        "abcdefghijklmnopqrstuvwxyz",
+        "b",
+        "ba",
+    ] {
+        keys.push(word.as_bytes().to_vec());
+    }
+    keys.sort();
+    let tree = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+    (tree, keys)
+}
+
+#[test]
+fn test_cursor_steps_both_ways() {
+    let (tree, keys) = cursor_test_tree();
+    let mut cursor = tree.cursor();
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(cursor.next(), Some((&key[..], &i)));
+    }
+    assert_eq!(cursor.next(), None);
+    assert_eq!(cursor.key(), None);
+    for (i, key) in keys.iter().enumerate().rev() {
+        assert_eq!(cursor.prev(), Some((&key[..], &i)));
+    }
+    assert_eq!(cursor.prev(), None);
+    // changing direction steps back over the entry just returned.
+    assert!(cursor.seek(b"ab"));
+    assert_eq!(cursor.next().unwrap().0, b"abc");
+    assert_eq!(cursor.prev().unwrap().0, b"ab");
+    assert_eq!(cursor.prev().unwrap().0, b"a");
+    assert!(!tree.cursor().seek(b"abcd"));
+    assert_eq!(Tree::<u8>::new().cursor().next(), None);
+}
+
+#[test]
+fn test_cursor_seek_matches_btree_map() {
+    use std::collections::BTreeMap;
+
+    let (tree, keys) = cursor_test_tree();
+    let map: BTreeMap<Vec<u8>, usize> = keys.iter().cloned().zip(0..).collect();
+    let mut cursor = tree.cursor();
+    let mut probes: Vec<Vec<u8>> = vec![vec![], vec![0], vec![0xff; 5], b"abd".to_vec()];
+    for i in 0..2_000u32 {
+        probes.push((i * 53).to_be_bytes()[..1 + i as usize % 4].to_vec());
+        probes.push(b"abcdefghijklmnopqrstuvwxyz"[..i as usize % 27].to_vec());
+    }
+    for probe in probes {
+        let ge = map.range(probe.clone()..).next();
+        assert_eq!(cursor.seek_ge(&probe), ge.is_some(), "{:?}", probe);
+        assert_eq!(cursor.key(), ge.map(|(k, _)| &k[..]));
+        assert_eq!(cursor.value(), ge.map(|(_, v)| v));
+        let le = map
+            .range::<Vec<u8>, _>((Bound::Unbounded, Bound::Included(probe.clone())))
+            .next_back();
+        assert_eq!(cursor.seek_le(&probe), le.is_some(), "{:?}", probe);
+        assert_eq!(cursor.key(), le.map(|(k, _)| &k[..]));
+        // the neighbours after a seek are the neighbours in the map.
+        let after = map
+            .range::<Vec<u8>, _>((Bound::Excluded(probe.clone()), Bound::Unbounded))
+            .next();
+        if le.is_some() {
+            assert_eq!(
+                cursor.next().map(|(k, _)| k.to_vec()),
+                after.map(|(k, _)| k.clone())
+            );
+        }
+    }
+}
+
+#[test]
+fn test_cursor_merge_join_in_lockstep() {
+    let left: Tree<u32> = (0..1_000u32).map(|i| ((i * 2).to_be_bytes(), i)).collect();
+    let right: Tree<u32> = (0..1_000u32).map(|i| ((i * 3).to_be_bytes(), i)).collect();
+    let (mut a, mut b) = (left.cursor(), right.cursor());
+    let (mut x, mut y) = (
+        a.next().map(|e| e.0.to_vec()),
+        b.next().map(|e| e.0.to_vec()),
+    );
+    let mut joined = vec![];
+    while let (Some(kx), Some(ky)) = (&x, &y) {
+        match kx.cmp(ky) {
+            Ordering::Equal => {
+                joined.push(u32::from_be_bytes(kx[..].try_into().unwrap()));
+                x = a.next().map(|e| e.0.to_vec());
+                y = b.next().map(|e| e.0.to_vec());
+            }
+            // the lagging side re-seeks straight to the other's key.
+            Ordering::Less => x = a.seek_ge(ky).then(|| a.key().unwrap().to_vec()),
+            Ordering::Greater => y = b.seek_ge(kx).then(|| b.key().unwrap().to_vec()),
+        }
+    }
+    assert!(joined.into_iter().eq((0..2_000).step_by(6)));
+}
+
+#[test]
+fn test_cursor_mut_removes_and_inserts() {
+    let mut tree: Tree<u32> = (0..100u32).map(|i| ((i * 10).to_be_bytes(), i)).collect();
+    let mut cursor = tree.cursor_mut();
+    // drop every odd entry and put a new one after every even one.
+    while let Some((key, val)) = cursor.next() {
+        let (key, val) = (u32::from_be_bytes(key.try_into().unwrap()), *val);
+        *cursor.value_mut().unwrap() += 1_000;
+        if val % 2 == 1 {
+            let removed = cursor.remove_current().unwrap();
+            assert_eq!(removed.1, val + 1_000);
+            cursor.prev();
+        } else {
+            cursor.insert_after(&(key + 5).to_be_bytes(), val);
+            assert_eq!(cursor.key(), Some(&key.to_be_bytes()[..]));
+            cursor.next();
+        }
+    }
+    assert_eq!(tree.len(), 100);
+    let expected = (0..100u32)
+        .step_by(2)
+        .flat_map(|i| [(i * 10, i + 1_000), (i * 10 + 5, i)]);
+    assert!(tree
+        .iter()
+        .map(|(k, v)| (u32::from_be_bytes(k[..].try_into().unwrap()), *v))
+        .eq(expected));
+}
+
+#[test]
+fn test_cursor_mut_writes_values_through_its_path() {
+    let words = ["a", "ab", "abc", "b", "ba", "c"];
+    let mut tree: Tree<usize> = words.iter().map(|w| (w, 0)).collect();
+    let mut cursor = tree.cursor_mut();
+    // writing a value keeps the position, so both directions go on from it.
+    for (i, word) in words.iter().enumerate() {
+        assert_eq!(cursor.next().unwrap().0, word.as_bytes());
+        *cursor.value_mut().unwrap() = i;
+        assert_eq!(cursor.value(), Some(&i));
+    }
+    assert_eq!(cursor.prev(), Some((&b"ba"[..], &4)));
+    *cursor.value_mut().unwrap() += 10;
+    assert_eq!(cursor.prev(), Some((&b"b"[..], &3)));
+    assert_eq!(cursor.next(), Some((&b"ba"[..], &14)));
+    assert!(tree.values().copied().eq([0, 1, 2, 3, 14, 5]));
+}
+
+#[test]
+fn test_tree_floor_ceiling_and_bounds() {
+    use std::collections::BTreeMap;
+
+    let (tree, keys) = cursor_test_tree();
+    let map: BTreeMap<Vec<u8>, usize> = keys.iter().cloned().zip(0..).collect();
+    let as_pair = |e: Option<(Key, &usize)>| e.map(|(k, v)| (k.to_vec(), *v));
+    let map_pair = |e: Option<(&Vec<u8>, &usize)>| e.map(|(k, v)| (k.clone(), *v));
+    let mut probes: Vec<Vec<u8>> = keys.iter().step_by(7).cloned().collect();
+    for i in 0..1_000u32 {
+        // probes that leave the tree partway down a path.
+        probes.push((i * 111 + 5).to_be_bytes().to_vec());
+        probes.push((i * 111).to_be_bytes()[..2].to_vec());
+    }
+    probes.extend([
+        b"aa".to_vec(),
+        b"abcz".to_vec(),
+        b"c".to_vec(),
+        vec![0xff; 9],
+    ]);
+    for probe in &probes {
+        let (lo, hi) = (
+            Bound::Included(probe.clone()),
+            Bound::Excluded(probe.clone()),
+        );
+        let floor = map
+            .range::<Vec<u8>, _>((Bound::Unbounded, lo.clone()))
+            .next_back();
+        let ceiling = map.range::<Vec<u8>, _>((lo, Bound::Unbounded)).next();
+        assert_eq!(as_pair(tree.floor(probe)), map_pair(floor), "{:?}", probe);
+        assert_eq!(
+            as_pair(tree.ceiling(probe)),
+            map_pair(ceiling),
+            "{:?}",
+            probe
+        );
+
+        let above = map
+            .range::<Vec<u8>, _>((hi.clone(), Bound::Unbounded))
+            .next();
+        let below = map.range::<Vec<u8>, _>((Bound::Unbounded, hi)).next_back();
+        let lower = tree.lower_bound(Bound::Excluded(probe));
+        assert_eq!(lower.key(), above.map(|(k, _)| &k[..]));
+        let upper = tree.upper_bound(Bound::Excluded(probe));
+        assert_eq!(upper.key(), below.map(|(k, _)| &k[..]));
+        assert_eq!(
+            tree.lower_bound(Bound::Included(probe)).key(),
+            ceiling.map(|(k, _)| &k[..])
+        );
+        assert_eq!(
+            tree.upper_bound(Bound::Included(probe)).key(),
+            floor.map(|(k, _)| &k[..])
+        );
+    }
+    assert_eq!(tree.lower_bound(Bound::Unbounded).key(), Some(&b""[..]));
+    assert_eq!(
+        tree.upper_bound(Bound::Unbounded).key(),
+        keys.last().map(|k| &k[..])
+    );
+    // the returned cursor keeps walking from the bound.
+    let mut cursor = tree.lower_bound(Bound::Excluded(b"a"));
+    assert_eq!(
+        cursor.next().map(|(k, _)| k.to_vec()),
+        Some(b"abc".to_vec())
+    );
+
+    // nothing past the bound leaves the cursor at that end instead of wrapping.
+    let (first, last) = (&keys[0][..], &keys[keys.len() - 1][..]);
+    let mut cursor = tree.lower_bound(Bound::Excluded(last));
+    assert_eq!(cursor.key(), None);
+    assert_eq!(cursor.next(), None);
+    assert_eq!(cursor.next(), None);
+    assert_eq!(cursor.prev().map(|(k, _)| k), Some(last));
+    assert_eq!(cursor.next(), None);
+    let mut cursor = tree.upper_bound(Bound::Excluded(first));
+    assert_eq!(cursor.prev(), None);
+    assert_eq!(cursor.next().map(|(k, _)| k), Some(first));
+    assert_eq!(cursor.prev(), None);
+    assert_eq!(cursor.prev(), None);
+    // a cursor from `cursor` still wraps around.
+    let mut cursor = tree.cursor();
+    assert!(cursor.seek_ge(last) && cursor.next().is_none());
+    assert_eq!(cursor.next().map(|(k, _)| k), Some(first));
+    let empty = Tree::<usize>::new();
+    assert_eq!(empty.lower_bound(Bound::Unbounded).next(), None);
+    assert_eq!(empty.upper_bound(Bound::Unbounded).prev(), None);
+}
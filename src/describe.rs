@@ -56,6 +56,12 @@ impl Describe {
     }
 }
 
+impl Default for Describe {
+    fn default() -> Self {
+        Describe::new()
+    }
+}
+
 pub trait Describer {
     fn describe(&self, describe: &mut Describe);
 }
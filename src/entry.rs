@@ -0,0 +1,303 @@
+use crate::{Key, Leaf, Node};
+
+/// A view into a single entry of a `Tree`, which is either occupied or vacant.
+///
+/// Returned by `Tree::entry` after it has found its way to the key, so reading,
+/// updating or removing the value does not walk the tree from the root again.
+pub enum Entry<'a, V> {
+    Occupied(OccupiedEntry<'a, V>),
+    Vacant(VacantEntry<'a, V>),
+}
+
+/// An entry whose key is in the tree.
+pub struct OccupiedEntry<'a, V> {
+    pub(crate) key: Key,
+    /// The highest node that removing the value restructures and the number of key
+    /// bytes consumed to reach it. The value is at most a few steps below it.
+    pub(crate) node: &'a mut Node<V>,
+    pub(crate) depth: usize,
+    pub(crate) count: &'a mut usize,
+}
+
+/// An entry whose key is not in the tree.
+pub struct VacantEntry<'a, V> {
+    pub(crate) key: Key,
+    /// The deepest node on the path of the key and the number of key bytes
+    /// consumed to reach it, which is where the insert picks up from.
+    pub(crate) node: &'a mut Node<V>,
+    pub(crate) depth: usize,
+    pub(crate) count: &'a mut usize,
+}
+
+impl<'a, V> Entry<'a, V> {
+    /// Inserts `default` if the entry is vacant and returns a reference to the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Inserts the result of `default` if the entry is vacant and returns a reference
+    /// to the value.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Like `or_insert_with`, but `default` is given the key of the entry.
+    pub fn or_insert_with_key<F: FnOnce(&Key) -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let val = default(&entry.key);
+                entry.insert(val)
+            }
+        }
+    }
+
+    /// Calls `f` with the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+
+    pub fn key(&self) -> &Key {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+impl<'a, V: Default> Entry<'a, V> {
+    /// Inserts `V::default()` if the entry is vacant and returns a reference to the value.
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, V> OccupiedEntry<'a, V> {
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    fn leaf(&self) -> &Leaf<V> {
+        let (mut node, mut depth) = (&*self.node, self.depth);
+        while depth < self.key.len() {
+            (node, depth) = node.next_node(&self.key, depth).unwrap();
+        }
+        node.leaf().unwrap()
+    }
+
+    fn leaf_mut(&mut self) -> &mut Leaf<V> {
+        leaf_below(self.node, &self.key, self.depth)
+    }
+
+    pub fn get(&self) -> &V {
+        &self.leaf().val
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.leaf_mut().val
+    }
+
+    /// Converts the entry into a reference to its value that lives as long as the
+    /// borrow of the tree.
+    pub fn into_mut(self) -> &'a mut V {
+        &mut leaf_below(self.node, &self.key, self.depth).val
+    }
+
+    /// Replaces the value of the entry, returning the old value.
+    pub fn insert(&mut self, val: V) -> V {
+        self.leaf_mut().insert(val)
+    }
+
+    /// Removes the entry from the tree, returning its value.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Removes the entry from the tree, returning its key and value.
+    ///
+    /// The removal starts from the node the entry holds, so the tree is not walked
+    /// from the root again.
+    pub fn remove_entry(self) -> (Key, V) {
+        let val = self.node.remove(&self.key, self.depth).unwrap();
+        *self.count -= 1;
+        (self.key, val)
+    }
+}
+
+fn leaf_below<'a, V>(mut node: &'a mut Node<V>, key: &[u8], mut depth: usize) -> &'a mut Leaf<V> {
+    while depth < key.len() {
+        (node, depth) = node.next_node_mut(key, depth).unwrap();
+    }
+    node.leaf_mut().unwrap()
+}
+
+impl<'a, V> VacantEntry<'a, V> {
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    pub fn into_key(self) -> Key {
+        self.key
+    }
+
+    /// Inserts the value of the entry and returns a reference to it.
+    pub fn insert(self, val: V) -> &'a mut V {
+        *self.count += 1;
+        self.node.insert_below(&self.key, self.depth, val)
+    }
+}
+
+impl<V> std::fmt::Debug for Entry<'_, V>
+where
+    V: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Entry::Occupied(entry) => f
+                .debug_tuple("Entry::Occupied")
+                .field(&entry.key)
+                .field(entry.get())
+                .finish(),
+            Entry::Vacant(entry) => f.debug_tuple("Entry::Vacant").field(&entry.key).finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::Tree;
+
+#[test]
+fn test_entry_counts_words() {
+    let mut tree = Tree::<usize>::new();
+    let text = "the cat and the hat and the bat sat on the mat";
+    for word in text.split(' ') {
+        *tree.entry(word.as_bytes()).or_default() += 1;
+    }
+    assert_eq!(tree.len(), 8);
+    assert_eq!(tree.get(b"the"), Some(&4));
+    assert_eq!(tree.get(b"and"), Some(&2));
+    assert_eq!(tree.get(b"mat"), Some(&1));
+    assert_eq!(tree.values().sum::<usize>(), 12);
+}
+
+#[test]
+fn test_entry_variants() {
+    let mut tree = Tree::<String>::new();
+    match tree.entry(b"apple") {
+        Entry::Vacant(entry) => {
+            assert_eq!(entry.key(), &Key::from("apple"));
+            entry.insert(String::from("red"));
+        }
+        Entry::Occupied(_) => panic!("expected a vacant entry"),
+    }
+    match tree.entry(b"apple") {
+        Entry::Occupied(mut entry) => {
+            assert_eq!(entry.get(), "red");
+            assert_eq!(entry.insert(String::from("green")), "red");
+            entry.get_mut().push_str("ish");
+        }
+        Entry::Vacant(_) => panic!("expected an occupied entry"),
+    }
+    assert_eq!(tree.get(b"apple").map(|s| s.as_str()), Some("greenish"));
+    assert_eq!(tree.len(), 1);
+
+    // keys that end inside a Branch or on an inner node are vacant until inserted.
+    assert!(matches!(tree.entry(b"app"), Entry::Vacant(_)));
+    assert!(matches!(tree.entry(b""), Entry::Vacant(_)));
+    assert_eq!(tree.len(), 1);
+    tree.entry(b"app").or_insert_with(|| String::from("short"));
+    tree.entry(b"apples")
+        .and_modify(|s| s.clear())
+        .or_insert_with_key(|key| format!("{:?}", key));
+    tree.entry(b"apple")
+        .and_modify(|s| s.make_ascii_uppercase())
+        .or_insert_with(|| unreachable!());
+    let got: Vec<(Key, &str)> = tree.iter().map(|(k, v)| (k, v.as_str())).collect();
+    assert_eq!(
+        got,
+        vec![
+            (Key::from("app"), "short"),
+            (Key::from("apple"), "GREENISH"),
+            (Key::from("apples"), "Key::from(\"apples\")"),
+        ]
+    );
+    assert_eq!(tree.len(), 3);
+}
+
+#[test]
+fn test_entry_matches_btree_map_entry() {
+    use std::collections::BTreeMap;
+
+    let mut tree = Tree::<usize>::new();
+    let mut btree = BTreeMap::<Vec<u8>, usize>::new();
+    for i in 0..20_000usize {
+        // repeats keys so that both arms of the entry are taken across node types.
+        let key = (i * 7919 % 7_000).to_be_bytes();
+        let got = *tree.entry(&key).and_modify(|v| *v += i).or_insert(i);
+        let expected = *btree
+            .entry(key.to_vec())
+            .and_modify(|v| *v += i)
+            .or_insert(i);
+        assert_eq!(got, expected);
+    }
+    assert_eq!(tree.len(), btree.len());
+    for (key, val) in btree.iter_mut() {
+        *val += 1;
+        *tree.get_mut(key).unwrap() += 1;
+    }
+    assert!(tree.iter().map(|(k, v)| (k.to_vec(), *v)).eq(btree));
+    assert_eq!(tree.get_mut(&7_000usize.to_be_bytes()), None);
+}
+
+#[test]
+fn test_entry_remove_matches_tree_remove() {
+    let mut by_entry = Tree::<usize>::new();
+    let mut by_key = Tree::<usize>::new();
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut keys = vec![];
+    for i in 0..5_000 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        // short keys of mixed lengths, so that many keys are prefixes of others.
+        let key = state.to_be_bytes()[..(state % 4) as usize + 1].to_vec();
+        by_entry.insert(&key, i);
+        by_key.insert(&key, i);
+        keys.push(key);
+    }
+    for key in keys.iter().step_by(3) {
+        let expected = by_key.remove(key);
+        match by_entry.entry(key) {
+            Entry::Occupied(entry) => {
+                let (removed, val) = entry.remove_entry();
+                assert_eq!(removed, Key::from(&key[..]));
+                assert_eq!(Some(val), expected);
+            }
+            Entry::Vacant(_) => assert_eq!(expected, None),
+        }
+        // the entry leaves the tree in the same shape as `Tree::remove`.
+        assert_eq!(by_entry.len(), by_key.len());
+    }
+    assert_eq!(by_entry.stats(), by_key.stats());
+    assert!(by_entry.iter().eq(by_key.iter()));
+
+    for key in &keys {
+        if let Entry::Occupied(entry) = by_entry.entry(key) {
+            assert_eq!(Some(entry.remove()), by_key.remove(key));
+        }
+    }
+    assert!(by_entry.is_empty());
+    assert!(by_entry.root.is_none());
+}
@@ -0,0 +1,149 @@
+use crate::{Automaton, Key, Tree};
+
+/// One row of the Levenshtein table: the edit distance from every prefix of the
+/// query to the key bytes consumed so far.
+#[derive(Clone)]
+struct Row(Vec<usize>);
+
+impl Row {
+    fn start(query: &[u8]) -> Row {
+        Row((0..=query.len()).collect())
+    }
+
+    /// The row after one more key byte.
+    fn step(&self, query: &[u8], byte: u8) -> Row {
+        let mut next = Vec::with_capacity(self.0.len());
+        next.push(self.0[0] + 1);
+        for (i, q) in query.iter().enumerate() {
+            let substitute = self.0[i] + (*q != byte) as usize;
+            let insert = self.0[i + 1] + 1;
+            let delete = next[i] + 1;
+            next.push(substitute.min(insert).min(delete));
+        }
+        Row(next)
+    }
+
+    /// The distance from the whole query to the key so far.
+    fn distance(&self) -> usize {
+        *self.0.last().unwrap()
+    }
+
+    /// No key extending the one so far can come within `max` edits once every
+    /// entry of the row is above it.
+    fn can_match(&self, max: usize) -> bool {
+        self.0.iter().any(|d| *d <= max)
+    }
+}
+
+/// Keys within `max_edits` of `query`, with a row of the Levenshtein table as
+/// the state.
+struct Levenshtein<'q> {
+    query: &'q [u8],
+    max_edits: usize,
+}
+
+impl<'q> Automaton for Levenshtein<'q> {
+    type State = Row;
+
+    fn start(&self) -> Row {
+        Row::start(self.query)
+    }
+
+    fn is_match(&self, row: &Row) -> bool {
+        row.distance() <= self.max_edits
+    }
+
+    fn can_match(&self, row: &Row) -> bool {
+        row.can_match(self.max_edits)
+    }
+
+    fn accept(&self, row: &Row, byte: u8) -> Row {
+        row.step(self.query, byte)
+    }
+}
+
+impl<V> Tree<V> {
+    /// Finds the keys within `max_edits` byte insertions, deletions or substitutions
+    /// of `query`, with their edit distance, in key order.
+    ///
+    /// This is `search` with a Levenshtein automaton: the walk carries a row of the
+    /// edit distance table down the tree and skips every subtree whose row is already
+    /// over the budget, so only the part of the tree near the query is visited.
+    ///
+    /// ```
+    /// let words: artoo::Tree<()> = ["apple", "apply", "ample", "maple", "applesauce"]
+    ///     .iter()
+    ///     .map(|w| (w, ()))
+    ///     .collect();
+    /// let found: Vec<(String, usize)> = words
+    ///     .fuzzy_search(b"appel", 2)
+    ///     .into_iter()
+    ///     .map(|(k, _, d)| (String::from_utf8(k.to_vec()).unwrap(), d))
+    ///     .collect();
+    /// assert_eq!(found, [("apple".into(), 2), ("apply".into(), 2)]);
+    /// ```
+    pub fn fuzzy_search(&self, query: &[u8], max_edits: usize) -> Vec<(Key, &V, usize)> {
+        let mut search = self.search(Levenshtein { query, max_edits });
+        let mut found = vec![];
+        while let Some((key, val, row)) = search.next_with_state() {
+            found.push((key, val, row.distance()));
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+fn levenshtein(a: &[u8], b: &[u8]) -> usize {
+    b.iter()
+        .fold(Row::start(a), |row, byte| row.step(a, *byte))
+        .distance()
+}
+
+#[test]
+fn test_levenshtein_distance() {
+    assert_eq!(levenshtein(b"", b""), 0);
+    assert_eq!(levenshtein(b"kitten", b"sitting"), 3);
+    assert_eq!(levenshtein(b"flaw", b"lawn"), 2);
+    assert_eq!(levenshtein(b"abc", b""), 3);
+    assert_eq!(levenshtein(b"", b"abcd"), 4);
+}
+
+#[test]
+fn test_fuzzy_search_matches_brute_force() {
+    let mut words = vec![];
+    let mut state = 0x51ed_270bu32;
+    for _ in 0..5_000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let len = 1 + state as usize % 9;
+        let word: Vec<u8> = (0..len)
+            .map(|i| b"abcde"[(state >> (i * 3)) as usize % 5])
+            .collect();
+        words.push(word);
+    }
+    words.extend([b"".to_vec(), b"abcdeabcdeabcdeabcdeabcdeabcde".to_vec()]);
+    let tree: Tree<usize> = words.iter().map(|w| (w, w.len())).collect();
+    for query in [
+        "",
+        "a",
+        "abc",
+        "edcba",
+        "aabbccdd",
+        "abcdeabcdeabcdeabcdeabcdeabcdx",
+    ] {
+        for max_edits in 0..=2 {
+            let found: Vec<(Vec<u8>, usize)> = tree
+                .fuzzy_search(query.as_bytes(), max_edits)
+                .into_iter()
+                .map(|(k, _, d)| (k.to_vec(), d))
+                .collect();
+            let expected: Vec<(Vec<u8>, usize)> = tree
+                .keys()
+                .map(|k| (k.to_vec(), levenshtein(query.as_bytes(), &k)))
+                .filter(|(_, d)| *d <= max_edits)
+                .collect();
+            assert_eq!(found, expected, "{:?} within {}", query, max_edits);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::automaton::ByteSet;
+use crate::{Automaton, PatternError};
+
+enum Token {
+    /// Any run of bytes, including none.
+    Star,
+    /// One byte from the set. `?` is the full set.
+    One(ByteSet),
+}
+
+/// A shell-style pattern over whole keys, as an `Automaton` for `Tree::search`.
+///
+/// `*` matches any run of bytes, `?` any single byte and `[...]` one byte from a
+/// class such as `[a-z0-9_]`, negated with `[!...]` or `[^...]`. A `\` escapes the
+/// next byte. Every other byte matches itself and the pattern must cover the whole
+/// key, so `user:*` is a prefix query and `*:session` a suffix query.
+///
+/// ```
+/// use artoo::{Automaton, Glob};
+///
+/// let glob = Glob::new("log-202[0-4]-??.txt").unwrap();
+/// let matches = |key: &[u8]| {
+///     let state = key.iter().fold(glob.start(), |s, b| glob.accept(&s, *b));
+///     glob.is_match(&state)
+/// };
+/// assert!(matches(b"log-2023-07.txt"));
+/// assert!(!matches(b"log-2025-07.txt"));
+/// ```
+pub struct Glob {
+    tokens: Vec<Token>,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Glob, PatternError> {
+        let pattern = pattern.as_bytes();
+        let mut tokens = vec![];
+        let mut i = 0;
+        while i < pattern.len() {
+            let token = match pattern[i] {
+                b'*' => {
+                    i += 1;
+                    // a run of stars matches what one does.
+                    if let Some(Token::Star) = tokens.last() {
+                        continue;
+                    }
+                    Token::Star
+                }
+                b'?' => {
+                    i += 1;
+                    Token::One(ByteSet::full())
+                }
+                b'[' => {
+                    let (set, end) = ByteSet::parse_class(pattern, i, b"!^")?;
+                    i = end;
+                    Token::One(set)
+                }
+                b'\\' => {
+                    let byte = *pattern
+                        .get(i + 1)
+                        .ok_or(PatternError::new(i, "trailing backslash"))?;
+                    i += 2;
+                    Token::One(ByteSet::byte(byte))
+                }
+                byte => {
+                    i += 1;
+                    Token::One(ByteSet::byte(byte))
+                }
+            };
+            tokens.push(token);
+        }
+        Ok(Glob { tokens })
+    }
+
+    /// Adds `pos` to `state` along with every position a star there can skip to.
+    fn enter(&self, state: &mut Vec<usize>, mut pos: usize) {
+        while !state.contains(&pos) {
+            state.push(pos);
+            match self.tokens.get(pos) {
+                Some(Token::Star) => pos += 1,
+                _ => return,
+            }
+        }
+    }
+}
+
+/// The state is the list of pattern positions the key so far can have reached.
+/// It is short for any usual pattern, so `accept` allocates that much rather than
+/// a flag for every position.
+impl Automaton for Glob {
+    type State = Vec<usize>;
+
+    fn start(&self) -> Vec<usize> {
+        let mut state = vec![];
+        self.enter(&mut state, 0);
+        state
+    }
+
+    fn is_match(&self, state: &Vec<usize>) -> bool {
+        state.contains(&self.tokens.len())
+    }
+
+    fn can_match(&self, state: &Vec<usize>) -> bool {
+        !state.is_empty()
+    }
+
+    fn accept(&self, state: &Vec<usize>, byte: u8) -> Vec<usize> {
+        let mut next = Vec::with_capacity(state.len() + 1);
+        for pos in state {
+            match self.tokens.get(*pos) {
+                Some(Token::Star) => self.enter(&mut next, *pos),
+                Some(Token::One(set)) if set.contains(byte) => self.enter(&mut next, pos + 1),
+                _ => (),
+            }
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+fn glob_matches(pattern: &str, key: &str) -> bool {
+    let glob = Glob::new(pattern).unwrap();
+    let state = key
+        .bytes()
+        .fold(glob.start(), |state, byte| glob.accept(&state, byte));
+    glob.is_match(&state)
+}
+
+#[test]
+fn test_glob_matches() {
+    for (pattern, key, expected) in [
+        ("", "", true),
+        ("", "a", false),
+        ("*", "", true),
+        ("*", "anything", true),
+        ("a*b", "ab", true),
+        ("a*b", "axxb", true),
+        ("a*b", "axxbx", false),
+        ("a**b*c", "abbbc", true),
+        ("?", "", false),
+        ("??", "ab", true),
+        ("user:*:session", "user:42:session", true),
+        ("user:*:session", "user::session", true),
+        ("user:*:session", "user:42:sessions", false),
+        ("*.[ch]", "main.c", true),
+        ("*.[ch]", "main.rs", false),
+        ("[!0-9]*", "x1", true),
+        ("[^0-9]*", "1x", false),
+        ("\\*\\?", "*?", true),
+        ("\\*", "a", false),
+        ("[*]", "*", true),
+    ] {
+        assert_eq!(
+            glob_matches(pattern, key),
+            expected,
+            "{:?} {:?}",
+            pattern,
+            key
+        );
+    }
+    assert_eq!(Glob::new("ab\\").err().map(|e| e.pos()), Some(2));
+    assert_eq!(Glob::new("a[bc").err().map(|e| e.pos()), Some(1));
+}
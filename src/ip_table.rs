@@ -0,0 +1,525 @@
+use crate::{ArtKey, Decoded, FromArtKey, Iter, Node, Tree};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+const FAMILY_V4: u8 = 4;
+const FAMILY_V6: u8 = 6;
+
+/// An IPv4 or IPv6 network: an address with the number of leading bits that
+/// name the network. The bits after the prefix are always zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IpNet {
+    addr: IpAddr,
+    len: u8,
+}
+
+impl IpNet {
+    /// The network of the first `len` bits of `addr`, or `None` if `len` is longer
+    /// than the address.
+    pub fn new(addr: IpAddr, len: u8) -> Option<IpNet> {
+        let (mut octets, width) = octets(addr);
+        if len as usize > width * 8 {
+            return None;
+        }
+        for (i, octet) in octets[..width].iter_mut().enumerate() {
+            let kept = (len as usize).saturating_sub(i * 8).min(8);
+            *octet &= !(0xffu16 >> kept) as u8;
+        }
+        Some(IpNet {
+            addr: from_octets(addr.is_ipv4(), octets),
+            len,
+        })
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        IpNet::new(addr, self.len) == Some(*self)
+    }
+}
+
+fn octets(addr: IpAddr) -> ([u8; 16], usize) {
+    let mut octets = [0; 16];
+    match addr {
+        IpAddr::V4(v4) => {
+            octets[..4].copy_from_slice(&v4.octets());
+            (octets, 4)
+        }
+        IpAddr::V6(v6) => (v6.octets(), 16),
+    }
+}
+
+fn from_octets(v4: bool, octets: [u8; 16]) -> IpAddr {
+    match v4 {
+        true => IpAddr::V4(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3])),
+        false => IpAddr::V6(Ipv6Addr::from(octets)),
+    }
+}
+
+impl From<IpAddr> for IpNet {
+    /// The network of the single address.
+    fn from(addr: IpAddr) -> IpNet {
+        let len = if addr.is_ipv4() { 32 } else { 128 };
+        IpNet { addr, len }
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.len)
+    }
+}
+
+/// The error for a string that is not an address with an optional `/len` suffix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseIpNetError;
+
+impl fmt::Display for ParseIpNetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid IP network syntax")
+    }
+}
+
+impl std::error::Error for ParseIpNetError {}
+
+impl FromStr for IpNet {
+    type Err = ParseIpNetError;
+
+    /// Parses `10.0.0.0/8` or `2001:db8::/32`. Host bits are cleared and a bare
+    /// address is the network of that one address.
+    fn from_str(s: &str) -> Result<IpNet, ParseIpNetError> {
+        let (addr, len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| ParseIpNetError)?;
+        match len {
+            None => Ok(IpNet::from(addr)),
+            Some(len) => {
+                let len = len.parse().map_err(|_| ParseIpNetError)?;
+                IpNet::new(addr, len).ok_or(ParseIpNetError)
+            }
+        }
+    }
+}
+
+/// A network is its family byte, the whole octets of its prefix and a tail byte
+/// for the bits of the prefix left in the next octet.
+///
+/// With `r` bits left over, the tail is `1 << r` with those bits below the marker
+/// bit, which packs every length from 0 to 7 bits and its bits into `1..=255`. A
+/// /19 is `[4, 10, 32, 0b1000]` for `10.32.0.0/19` and an IPv6 host route takes 18
+/// bytes, so the tree branches on whole octets with its wide nodes. The tail of a
+/// network sits among the octets of the longer networks inside the same whole
+/// octets, which means a network's key is not a prefix of the keys inside it and
+/// `IpTable` probes the tails itself at every octet on the way down.
+impl ArtKey for IpNet {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.with_encoded(|bytes| out.extend_from_slice(bytes))
+    }
+
+    fn with_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        let mut buf = [0u8; 1 + 16 + 1];
+        let (octets, _) = octets(self.addr);
+        let whole = self.len as usize / 8;
+        buf[0] = family(self.addr);
+        buf[1..1 + whole].copy_from_slice(&octets[..whole]);
+        buf[1 + whole] = tail(octets.get(whole).copied().unwrap_or(0), self.len % 8);
+        f(&buf[..2 + whole])
+    }
+}
+
+fn family(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => FAMILY_V4,
+        IpAddr::V6(_) => FAMILY_V6,
+    }
+}
+
+/// The tail byte for the first `bits` bits of `octet`.
+fn tail(octet: u8, bits: u8) -> u8 {
+    match bits {
+        0 => 1,
+        _ => (1 << bits) | (octet >> (8 - bits)),
+    }
+}
+
+impl FromArtKey for IpNet {
+    fn decode(bytes: &[u8]) -> Option<IpNet> {
+        let (family, rest) = bytes.split_first()?;
+        let (tail, whole) = rest.split_last()?;
+        let (v4, width) = match *family {
+            FAMILY_V4 => (true, 4),
+            FAMILY_V6 => (false, 16),
+            _ => return None,
+        };
+        let bits = tail.checked_ilog2()? as u8;
+        if whole.len() > width || (whole.len() == width && bits > 0) {
+            return None;
+        }
+        let mut octets = [0u8; 16];
+        octets[..whole.len()].copy_from_slice(whole);
+        if bits > 0 {
+            octets[whole.len()] = (tail ^ (1 << bits)) << (8 - bits);
+        }
+        Some(IpNet {
+            addr: from_octets(v4, octets),
+            len: (whole.len() * 8) as u8 + bits,
+        })
+    }
+}
+
+/// Descends from `node`, which `depth` bytes of `key` lead to, to the value of
+/// `key`.
+fn get_below<'a, V>(mut node: &'a Node<V>, key: &[u8], mut depth: usize) -> Option<&'a V> {
+    while depth < key.len() {
+        (node, depth) = node.next_node(key, depth)?;
+    }
+    node.leaf().map(|leaf| &leaf.val)
+}
+
+/// A routing table from IP networks to values, with longest prefix match lookups.
+///
+/// IPv4 and IPv6 networks live side by side and never match each other.
+///
+/// ```
+/// use artoo::IpTable;
+/// let mut table = IpTable::new();
+/// table.insert("10.0.0.0/8".parse().unwrap(), "corp");
+/// table.insert("10.32.0.0/19".parse().unwrap(), "lab");
+/// let (net, route) = table.lookup("10.32.31.7".parse().unwrap()).unwrap();
+/// assert_eq!((net.to_string(), *route), ("10.32.0.0/19".to_string(), "lab"));
+/// let (net, _) = table.lookup("10.32.32.7".parse().unwrap()).unwrap();
+/// assert_eq!(net.to_string(), "10.0.0.0/8");
+/// ```
+pub struct IpTable<V> {
+    tree: Tree<V>,
+}
+
+impl<V> IpTable<V> {
+    pub fn new() -> IpTable<V> {
+        IpTable { tree: Tree::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn insert(&mut self, net: IpNet, val: V) -> Option<V> {
+        net.with_encoded(|key| self.tree.insert(key, val))
+    }
+
+    pub fn remove(&mut self, net: IpNet) -> Option<V> {
+        net.with_encoded(|key| self.tree.remove(key))
+    }
+
+    /// The value stored for exactly `net`.
+    pub fn get(&self, net: IpNet) -> Option<&V> {
+        net.with_encoded(|key| self.tree.get(key))
+    }
+
+    /// The most specific network containing `addr`.
+    pub fn lookup(&self, addr: IpAddr) -> Option<(IpNet, &V)> {
+        self.containing(IpNet::from(addr)).pop()
+    }
+
+    /// The networks containing `net`, `net` itself included, from the least to the
+    /// most specific.
+    pub fn covering(&self, net: IpNet) -> std::vec::IntoIter<(IpNet, &V)> {
+        self.containing(net).into_iter()
+    }
+
+    /// The networks inside `net`, `net` itself included, in address order.
+    pub fn covered_by(&self, net: IpNet) -> std::vec::IntoIter<(IpNet, &V)> {
+        let (octets, _) = octets(net.addr);
+        let whole = net.len as usize / 8;
+        let mut prefix = vec![family(net.addr)];
+        prefix.extend_from_slice(&octets[..whole]);
+        let mut inside: Vec<(IpNet, &V)> = Decoded::new(self.tree.prefix_iter(&prefix))
+            .filter(|(sub, _): &(IpNet, _)| sub.len >= net.len && net.contains(sub.addr))
+            .collect();
+        inside.sort_by_key(|(sub, _)| (sub.addr, sub.len));
+        inside.into_iter()
+    }
+
+    /// The networks containing `net`, shortest first.
+    ///
+    /// The walk follows the octets of `net` down the tree and at each one probes
+    /// the tails of the up to eight prefix lengths that end within the next octet,
+    /// which are single children of the node the octets lead to.
+    fn containing(&self, net: IpNet) -> Vec<(IpNet, &V)> {
+        let (octets, width) = octets(net.addr);
+        let mut key = [0u8; 1 + 16 + 1];
+        key[0] = family(net.addr);
+        key[1..1 + width].copy_from_slice(&octets[..width]);
+        let path = &key[..1 + width];
+        let (mut node, mut depth) = (&self.tree.root, 0);
+        let mut stuck = false;
+        let mut found = vec![];
+        for whole in 0..=width {
+            let end = 1 + whole;
+            while !stuck && depth < end {
+                match node.next_node(path, depth) {
+                    Some((next, at)) if at <= end => (node, depth) = (next, at),
+                    // a Branch runs past the octet, its sequence is checked below.
+                    Some(_) => break,
+                    None => stuck = true,
+                }
+            }
+            if stuck && !matches!(node, Node::Branch(_)) {
+                break;
+            }
+            let mut probe = key;
+            probe[..end].copy_from_slice(&path[..end]);
+            for bits in 0..8 {
+                let len = whole * 8 + bits;
+                if len > net.len as usize {
+                    return found;
+                }
+                probe[end] = tail(octets.get(whole).copied().unwrap_or(0), bits as u8);
+                if let Some(val) = get_below(node, &probe[..end + 1], depth) {
+                    found.push((IpNet::new(net.addr, len as u8).unwrap(), val));
+                }
+            }
+        }
+        found
+    }
+
+    /// Every network in the table, IPv4 before IPv6. Within a family the order is
+    /// that of the keys, which groups networks by their whole octets but puts a
+    /// tail among the octets of longer networks, so it is not address order.
+    pub fn iter(&self) -> Decoded<IpNet, Iter<'_, V>> {
+        Decoded::new(self.tree.iter())
+    }
+}
+
+impl<V> Default for IpTable<V> {
+    fn default() -> Self {
+        IpTable::new()
+    }
+}
+
+#[cfg(test)]
+fn net(s: &str) -> IpNet {
+    s.parse().unwrap()
+}
+
+#[test]
+fn test_ip_net_parses_and_encodes_bit_prefixes() {
+    assert_eq!(net("10.32.31.7/19").to_string(), "10.32.0.0/19");
+    assert_eq!(net("10.1.2.3").to_string(), "10.1.2.3/32");
+    assert_eq!(net("2001:db8::1/32").to_string(), "2001:db8::/32");
+    assert_eq!(net("0.0.0.0/0").prefix_len(), 0);
+    for bad in ["10.0.0.0/33", "::/129", "10.0.0/8", "10.0.0.0/x", ""] {
+        assert_eq!(bad.parse::<IpNet>(), Err(ParseIpNetError), "{:?}", bad);
+    }
+    let key = net("192.0.0.0/3").to_key();
+    assert_eq!(key.as_slice(), &[FAMILY_V4, 0b1110]);
+    let key = net("10.32.0.0/19").to_key();
+    assert_eq!(key.as_slice(), &[FAMILY_V4, 10, 32, 0b1000]);
+    assert_eq!(
+        net("10.1.2.3").to_key().as_slice(),
+        &[FAMILY_V4, 10, 1, 2, 3, 1]
+    );
+    assert_eq!(net("2001:db8::1").to_key().len(), 18);
+    for bad in [
+        &[FAMILY_V4][..],
+        &[FAMILY_V4, 0],
+        &[FAMILY_V4, 1, 2, 3, 4, 3],
+        &[5, 1],
+    ] {
+        assert_eq!(IpNet::decode(bad), None, "{:?}", bad);
+    }
+    for s in [
+        "10.32.0.0/19",
+        "0.0.0.0/0",
+        "::/0",
+        "2001:db8:ff00::/40",
+        "::1",
+        "10.32.16.128/25",
+        "255.255.255.254/31",
+    ] {
+        assert_eq!(IpNet::decode(&net(s).to_key()), Some(net(s)));
+    }
+    assert!(net("10.32.0.0/19").contains("10.32.31.255".parse().unwrap()));
+    assert!(!net("10.32.0.0/19").contains("10.32.32.0".parse().unwrap()));
+    assert!(!net("0.0.0.0/0").contains("::1".parse().unwrap()));
+}
+
+#[test]
+fn test_ip_table_longest_prefix_match() {
+    let mut table = IpTable::new();
+    let routes = [
+        "0.0.0.0/0",
+        "10.0.0.0/8",
+        "10.32.0.0/11",
+        "10.32.0.0/19",
+        "10.32.16.0/20",
+        "10.32.16.128/25",
+        "192.168.1.1/32",
+        "::/0",
+        "2001:db8::/32",
+        "2001:db8:abcd::/47",
+    ];
+    for (i, route) in routes.iter().enumerate() {
+        assert_eq!(table.insert(net(route), i), None);
+    }
+    assert_eq!(table.len(), routes.len());
+    // checked against a scan of every route that contains the address.
+    let mut state = 0x9e37_79b9u32;
+    for _ in 0..5_000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let addr = match state % 3 {
+            0 => IpAddr::V4(Ipv4Addr::from(state)),
+            1 => IpAddr::V4(Ipv4Addr::from(0x0a20_0000 | (state & 0x3fff))),
+            _ => IpAddr::V6(Ipv6Addr::from(
+                0x2001_0db8_abcc_u128 << 80 | (state as u128 * 0x1_0001) << 64,
+            )),
+        };
+        let expected = routes
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| net(r).contains(addr))
+            .max_by_key(|(_, r)| net(r).prefix_len())
+            .map(|(i, r)| (net(r), i));
+        assert_eq!(
+            table.lookup(addr).map(|(n, v)| (n, *v)),
+            expected,
+            "{}",
+            addr
+        );
+    }
+    assert_eq!(table.remove(net("0.0.0.0/0")), Some(0));
+    assert_eq!(table.lookup("11.0.0.1".parse().unwrap()), None);
+    assert_eq!(table.get(net("10.32.0.0/19")), Some(&3));
+    assert_eq!(table.get(net("10.32.0.0/18")), None);
+}
+
+#[test]
+fn test_ip_table_covering_and_covered_by() {
+    let mut table = IpTable::new();
+    for route in [
+        "10.0.0.0/8",
+        "10.32.0.0/11",
+        "10.32.0.0/19",
+        "10.32.64.0/19",
+        "10.64.0.0/10",
+        "::/0",
+    ] {
+        table.insert(net(route), ());
+    }
+    let names = |nets: Vec<IpNet>| nets.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+    let covering: Vec<IpNet> = table
+        .covering(net("10.32.1.0/24"))
+        .map(|(n, _)| n)
+        .collect();
+    assert_eq!(
+        names(covering),
+        ["10.0.0.0/8", "10.32.0.0/11", "10.32.0.0/19"]
+    );
+    let covering: Vec<IpNet> = table
+        .covering(net("10.32.0.0/11"))
+        .map(|(n, _)| n)
+        .collect();
+    assert_eq!(names(covering), ["10.0.0.0/8", "10.32.0.0/11"]);
+    let inside: Vec<IpNet> = table
+        .covered_by(net("10.32.0.0/11"))
+        .map(|(n, _)| n)
+        .collect();
+    assert_eq!(
+        names(inside),
+        ["10.32.0.0/11", "10.32.0.0/19", "10.32.64.0/19"]
+    );
+    let inside: Vec<IpNet> = table
+        .covered_by(net("10.0.0.0/9"))
+        .map(|(n, _)| n)
+        .collect();
+    assert_eq!(inside.len(), 4);
+    assert_eq!(table.covered_by(net("::/0")).count(), 1);
+    assert_eq!(table.iter().next_back().map(|(n, _)| n), Some(net("::/0")));
+}
+
+#[test]
+fn test_ip_table_matches_a_scan_at_every_length() {
+    let mut state = 0x2545_f491u32;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+    // networks of every length below a few /8s, so that tails and octets mix.
+    let mut table = IpTable::new();
+    let mut routes = vec![];
+    for _ in 0..2_000 {
+        let bits = next();
+        let addr = IpAddr::V4(Ipv4Addr::from(bits & 0x0301_ffff | 0x0a00_0000));
+        let route = IpNet::new(addr, (bits >> 24) as u8 % 33).unwrap();
+        if table.insert(route, ()).is_none() {
+            routes.push(route);
+        }
+    }
+    for _ in 0..2_000 {
+        let bits = next();
+        let probe = IpNet::new(
+            IpAddr::V4(Ipv4Addr::from(bits & 0x0301_ffff | 0x0a00_0000)),
+            (bits >> 24) as u8 % 33,
+        )
+        .unwrap();
+        let mut covering: Vec<IpNet> = routes
+            .iter()
+            .filter(|r| r.len <= probe.len && r.contains(probe.addr))
+            .copied()
+            .collect();
+        covering.sort_by_key(|r| r.len);
+        let found: Vec<IpNet> = table.covering(probe).map(|(n, _)| n).collect();
+        assert_eq!(found, covering, "{}", probe);
+        assert_eq!(
+            table.lookup(probe.addr).map(|(n, _)| n),
+            routes
+                .iter()
+                .filter(|r| r.contains(probe.addr))
+                .max_by_key(|r| r.len)
+                .copied()
+        );
+        let mut inside: Vec<IpNet> = routes
+            .iter()
+            .filter(|r| r.len >= probe.len && probe.contains(r.addr))
+            .copied()
+            .collect();
+        inside.sort_by_key(|r| (r.addr, r.len));
+        let found: Vec<IpNet> = table.covered_by(probe).map(|(n, _)| n).collect();
+        assert_eq!(found, inside, "{}", probe);
+    }
+}
+
+#[test]
+fn test_ip_table_branches_on_octets() {
+    let mut table = IpTable::new();
+    for third in 0..=255u8 {
+        let addr = IpAddr::V4(Ipv4Addr::new(10, 1, third, 0));
+        table.insert(IpNet::new(addr, 24).unwrap(), third);
+    }
+    // the /24s share a node that takes their third octets as its children and
+    // each is three nodes down, where one byte per bit took 25.
+    let stats = table.tree.stats();
+    assert_eq!(stats.node256, 1);
+    assert_eq!(stats.leaf_depths, [0, 0, 0, 256]);
+    assert_eq!(
+        table.lookup("10.1.200.7".parse().unwrap()),
+        Some((net("10.1.200.0/24"), &200))
+    );
+}
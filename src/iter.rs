@@ -0,0 +1,574 @@
+use crate::{BoxNode, Key, Node, Seek};
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+fn push_children<'a, V>(stack: &mut Vec<(Key, &'a Node<V>)>, key: &Key, bn: &'a BoxNode<V>) {
+    // reversed so that the smallest byte is popped first.
+    for (byte, child) in bn.iter().rev() {
+        stack.push((key.concat(byte), child));
+    }
+}
+
+/// Compares a Branch sequence with the rest of a bound.
+///
+/// `Equal` means the whole sequence is a prefix of the bound. Otherwise every key
+/// below the Branch sorts on the returned side of the bound.
+pub(crate) fn compare_sequence(sequence: &[u8], rest: &[u8]) -> Ordering {
+    if rest.starts_with(sequence) {
+        return Ordering::Equal;
+    }
+    match sequence.iter().zip(rest).find(|(a, b)| a != b) {
+        Some((a, b)) => a.cmp(b),
+        // the bound ends within the sequence, so every key below it is longer.
+        None => Ordering::Greater,
+    }
+}
+
+enum Back<'a, V> {
+    Node(Key, &'a Node<V>),
+    Leaf(Key, &'a V),
+}
+
+fn push_children_back<'a, V>(stack: &mut Vec<Back<'a, V>>, key: &Key, bn: &'a BoxNode<V>) {
+    // in byte order so that the largest byte is popped first.
+    for (byte, child) in bn.iter() {
+        stack.push(Back::Node(key.concat(byte), child));
+    }
+}
+
+/// The pending nodes of a walk over the tree from both ends.
+///
+/// The front stack pops entries in ascending key order and the back stack in
+/// descending key order. Callers are responsible for stopping once they meet.
+pub(crate) struct Walk<'a, V> {
+    front: Vec<(Key, &'a Node<V>)>,
+    back: Vec<Back<'a, V>>,
+}
+
+impl<'a, V> Walk<'a, V> {
+    pub(crate) fn new(root: &'a Node<V>) -> Walk<'a, V> {
+        Walk {
+            front: vec![(Key::Empty, root)],
+            back: vec![Back::Node(Key::Empty, root)],
+        }
+    }
+
+    /// A walk over the subtree below `node`, whose path from the root is `key`.
+    pub(crate) fn at(key: Key, node: &'a Node<V>) -> Walk<'a, V> {
+        Walk {
+            front: vec![(key.clone(), node)],
+            back: vec![Back::Node(key, node)],
+        }
+    }
+
+    pub(crate) fn empty() -> Walk<'a, V> {
+        Walk {
+            front: vec![],
+            back: vec![],
+        }
+    }
+
+    /// Starts the front of the walk at the first key within `lower`.
+    ///
+    /// The descent follows `find_child` along the bound and stacks every larger
+    /// sibling on the way down, so nothing before the bound is ever visited.
+    pub(crate) fn seek_front(&mut self, root: &'a Node<V>, lower: Bound<&[u8]>) {
+        self.front.clear();
+        let (bound, inclusive) = match lower {
+            Bound::Unbounded => {
+                self.front.push((Key::Empty, root));
+                return;
+            }
+            Bound::Included(bound) => (bound, true),
+            Bound::Excluded(bound) => (bound, false),
+        };
+        let mut node = root;
+        let mut key = Key::Empty;
+        let mut depth = 0;
+        while depth < bound.len() {
+            let seek = Seek::at(bound, depth);
+            let bn = match node {
+                Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => bn,
+                Node::Branch(branch) => {
+                    match compare_sequence(branch.sequence(), seek.tail()) {
+                        // the sequence is part of the bound, keep descending.
+                        Ordering::Equal => {
+                            key = key.concat_slice(branch.sequence());
+                            depth += branch.sequence.len();
+                            node = &branch.node;
+                            continue;
+                        }
+                        Ordering::Greater => self.front.push((key, node)),
+                        Ordering::Less => (),
+                    }
+                    return;
+                }
+                // a leaf here is shorter than the bound and sorts before it.
+                Node::None | Node::Leaf(_) => return,
+            };
+            for (b, child) in bn.iter().rev() {
+                if b <= seek.byte {
+                    break;
+                }
+                self.front.push((key.concat(b), child));
+            }
+            match node.find_child(seek) {
+                Some(child) => {
+                    node = child;
+                    key = key.concat(seek.byte);
+                    depth += 1;
+                }
+                None => return,
+            }
+        }
+        // everything below `node` sorts after the bound.
+        match node {
+            _ if inclusive => self.front.push((key, node)),
+            Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => {
+                push_children(&mut self.front, &key, bn)
+            }
+            Node::Branch(_) => self.front.push((key, node)),
+            Node::None | Node::Leaf(_) => (),
+        }
+    }
+
+    /// Starts the back of the walk at the last key within `upper`.
+    pub(crate) fn seek_back(&mut self, root: &'a Node<V>, upper: Bound<&[u8]>) {
+        self.back.clear();
+        let (bound, inclusive) = match upper {
+            Bound::Unbounded => {
+                self.back.push(Back::Node(Key::Empty, root));
+                return;
+            }
+            Bound::Included(bound) => (bound, true),
+            Bound::Excluded(bound) => (bound, false),
+        };
+        let mut node = root;
+        let mut key = Key::Empty;
+        let mut depth = 0;
+        while depth < bound.len() {
+            let seek = Seek::at(bound, depth);
+            let bn = match node {
+                Node::BoxNode(bn) => bn,
+                Node::BoxNodeLeaf(bn, leaf) => {
+                    // the node's own key is a strict prefix of the bound.
+                    self.back.push(Back::Leaf(key.clone(), &leaf.val));
+                    bn
+                }
+                Node::Leaf(leaf) => {
+                    self.back.push(Back::Leaf(key, &leaf.val));
+                    return;
+                }
+                Node::Branch(branch) => {
+                    match compare_sequence(branch.sequence(), seek.tail()) {
+                        Ordering::Equal => {
+                            key = key.concat_slice(branch.sequence());
+                            depth += branch.sequence.len();
+                            node = &branch.node;
+                            continue;
+                        }
+                        Ordering::Less => self.back.push(Back::Node(key, node)),
+                        Ordering::Greater => (),
+                    }
+                    return;
+                }
+                Node::None => return,
+            };
+            for (b, child) in bn.iter() {
+                if b >= seek.byte {
+                    break;
+                }
+                self.back.push(Back::Node(key.concat(b), child));
+            }
+            match node.find_child(seek) {
+                Some(child) => {
+                    node = child;
+                    key = key.concat(seek.byte);
+                    depth += 1;
+                }
+                None => return,
+            }
+        }
+        // everything below `node` sorts after the bound, only its own value may remain.
+        match node {
+            Node::Leaf(leaf) | Node::BoxNodeLeaf(_, leaf) if inclusive => {
+                self.back.push(Back::Leaf(key, &leaf.val))
+            }
+            _ => (),
+        }
+    }
+
+    pub(crate) fn next_front(&mut self) -> Option<(Key, &'a V)> {
+        while let Some((key, node)) = self.front.pop() {
+            match node {
+                Node::None => continue,
+                Node::Leaf(leaf) => return Some((key, &leaf.val)),
+                Node::BoxNode(bn) => push_children(&mut self.front, &key, bn),
+                Node::BoxNodeLeaf(bn, leaf) => {
+                    // a node's own value sorts before everything below it.
+                    push_children(&mut self.front, &key, bn);
+                    return Some((key, &leaf.val));
+                }
+                Node::Branch(branch) => {
+                    self.front
+                        .push((key.concat_slice(branch.sequence()), &branch.node));
+                }
+            }
+        }
+        None
+    }
+
+    pub(crate) fn next_back(&mut self) -> Option<(Key, &'a V)> {
+        while let Some(back) = self.back.pop() {
+            let (key, node) = match back {
+                Back::Leaf(key, val) => return Some((key, val)),
+                Back::Node(key, node) => (key, node),
+            };
+            match node {
+                Node::None => continue,
+                Node::Leaf(leaf) => return Some((key, &leaf.val)),
+                Node::BoxNode(bn) => push_children_back(&mut self.back, &key, bn),
+                Node::BoxNodeLeaf(bn, leaf) => {
+                    // a node's own value sorts after everything below it in reverse.
+                    self.back.push(Back::Leaf(key.clone(), &leaf.val));
+                    push_children_back(&mut self.back, &key, bn);
+                }
+                Node::Branch(branch) => {
+                    self.back.push(Back::Node(
+                        key.concat_slice(branch.sequence()),
+                        &branch.node,
+                    ));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// An iterator over the entries of a `Tree` in lexicographic key order.
+///
+/// Keys are rebuilt one byte at a time while descending, so each item owns its `Key`.
+pub struct Iter<'a, V> {
+    walk: Walk<'a, V>,
+    remaining: usize,
+}
+
+impl<'a, V> Iter<'a, V> {
+    pub(crate) fn new(root: &'a Node<V>, len: usize) -> Iter<'a, V> {
+        Iter {
+            walk: Walk::new(root),
+            remaining: len,
+        }
+    }
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (Key, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // both ends share the same entries, so the count decides when they meet.
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.walk.next_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Iter<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.walk.next_back()
+    }
+}
+
+impl<'a, V> ExactSizeIterator for Iter<'a, V> {}
+
+/// A double-ended iterator over the entries of a `Tree` within a range of keys.
+///
+/// Created by `Tree::range`.
+pub struct Range<'a, V> {
+    walk: Walk<'a, V>,
+    lower: Bound<Key>,
+    upper: Bound<Key>,
+    // the last keys handed out by each end, so the two ends never cross.
+    last_front: Option<Key>,
+    last_back: Option<Key>,
+}
+
+impl<'a, V> Range<'a, V> {
+    pub(crate) fn new(root: &'a Node<V>, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Range<'a, V> {
+        let mut walk = Walk::empty();
+        walk.seek_front(root, lower);
+        walk.seek_back(root, upper);
+        Range {
+            walk,
+            lower: lower.map(Key::from),
+            upper: upper.map(Key::from),
+            last_front: None,
+            last_back: None,
+        }
+    }
+
+    /// Iterates every entry below `node`, whose path from the root is `key`.
+    pub(crate) fn subtree(key: Key, node: &'a Node<V>) -> Range<'a, V> {
+        Range {
+            walk: Walk::at(key, node),
+            lower: Bound::Unbounded,
+            upper: Bound::Unbounded,
+            last_front: None,
+            last_back: None,
+        }
+    }
+
+    pub(crate) fn empty() -> Range<'a, V> {
+        Range {
+            walk: Walk::empty(),
+            lower: Bound::Unbounded,
+            upper: Bound::Unbounded,
+            last_front: None,
+            last_back: None,
+        }
+    }
+
+    fn finish(&mut self) -> Option<(Key, &'a V)> {
+        self.walk = Walk::empty();
+        None
+    }
+}
+
+impl<'a, V> Iterator for Range<'a, V> {
+    type Item = (Key, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, val) = self.walk.next_front()?;
+        let beyond_upper = match &self.upper {
+            Bound::Included(upper) => key > *upper,
+            Bound::Excluded(upper) => key >= *upper,
+            Bound::Unbounded => false,
+        };
+        if beyond_upper || self.last_back.as_ref().is_some_and(|back| key >= *back) {
+            return self.finish();
+        }
+        self.last_front = Some(key.clone());
+        Some((key, val))
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Range<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (key, val) = self.walk.next_back()?;
+        let beyond_lower = match &self.lower {
+            Bound::Included(lower) => key < *lower,
+            Bound::Excluded(lower) => key <= *lower,
+            Bound::Unbounded => false,
+        };
+        if beyond_lower || self.last_front.as_ref().is_some_and(|front| key <= *front) {
+            return self.finish();
+        }
+        self.last_back = Some(key.clone());
+        Some((key, val))
+    }
+}
+
+fn push_children_mut<'a, V>(
+    stack: &mut Vec<(Key, &'a mut Node<V>)>,
+    key: &Key,
+    bn: &'a mut BoxNode<V>,
+) {
+    for (byte, child) in bn.iter_mut().rev() {
+        stack.push((key.concat(byte), child));
+    }
+}
+
+/// A mutable iterator over the entries of a `Tree` in lexicographic key order.
+pub struct IterMut<'a, V> {
+    stack: Vec<(Key, &'a mut Node<V>)>,
+    remaining: usize,
+}
+
+impl<'a, V> IterMut<'a, V> {
+    pub(crate) fn new(root: &'a mut Node<V>, len: usize) -> IterMut<'a, V> {
+        IterMut {
+            stack: vec![(Key::Empty, root)],
+            remaining: len,
+        }
+    }
+}
+
+impl<'a, V> Iterator for IterMut<'a, V> {
+    type Item = (Key, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((key, node)) = self.stack.pop() {
+            match node {
+                Node::None => continue,
+                Node::Leaf(leaf) => {
+                    self.remaining -= 1;
+                    return Some((key, &mut leaf.val));
+                }
+                Node::BoxNode(bn) => push_children_mut(&mut self.stack, &key, bn),
+                Node::BoxNodeLeaf(bn, leaf) => {
+                    push_children_mut(&mut self.stack, &key, bn);
+                    self.remaining -= 1;
+                    return Some((key, &mut leaf.val));
+                }
+                Node::Branch(branch) => {
+                    let key = key.concat_slice(branch.sequence());
+                    self.stack.push((key, &mut branch.node));
+                }
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, V> ExactSizeIterator for IterMut<'a, V> {}
+
+/// An owning iterator over the entries of a `Tree` in lexicographic key order.
+pub struct IntoIter<V> {
+    stack: Vec<(Key, Node<V>)>,
+    remaining: usize,
+}
+
+impl<V> IntoIter<V> {
+    pub(crate) fn new(root: Node<V>, len: usize) -> IntoIter<V> {
+        IntoIter {
+            stack: vec![(Key::Empty, root)],
+            remaining: len,
+        }
+    }
+
+    fn push_children(&mut self, key: &Key, bn: BoxNode<V>) {
+        let children: Vec<(u8, Node<V>)> = bn.into_iter().collect();
+        for (byte, child) in children.into_iter().rev() {
+            self.stack.push((key.concat(byte), child));
+        }
+    }
+}
+
+impl<V> Iterator for IntoIter<V> {
+    type Item = (Key, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((key, node)) = self.stack.pop() {
+            match node {
+                Node::None => continue,
+                Node::Leaf(leaf) => {
+                    self.remaining -= 1;
+                    return Some((key, leaf.val));
+                }
+                Node::BoxNode(bn) => self.push_children(&key, bn),
+                Node::BoxNodeLeaf(bn, leaf) => {
+                    self.push_children(&key, bn);
+                    self.remaining -= 1;
+                    return Some((key, leaf.val));
+                }
+                Node::Branch(branch) => {
+                    let key = key.concat_slice(branch.sequence());
+                    self.stack.push((key, branch.node));
+                }
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<V> ExactSizeIterator for IntoIter<V> {}
+
+/// A double-ended iterator over the entries of a `Tree` that start with a prefix.
+///
+/// Created by `Tree::prefix_iter`.
+pub struct PrefixIter<'a, V>(pub(crate) Range<'a, V>);
+
+impl<'a, V> Iterator for PrefixIter<'a, V> {
+    type Item = (Key, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for PrefixIter<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+/// An iterator over the keys of a `Tree` in lexicographic order.
+pub struct Keys<'a, V>(pub(crate) Iter<'a, V>);
+
+impl<'a, V> Iterator for Keys<'a, V> {
+    type Item = Key;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Keys<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<'a, V> ExactSizeIterator for Keys<'a, V> {}
+
+/// An iterator over the values of a `Tree` in key order.
+pub struct Values<'a, V>(pub(crate) Iter<'a, V>);
+
+impl<'a, V> Iterator for Values<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, val)| val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Values<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, val)| val)
+    }
+}
+
+impl<'a, V> ExactSizeIterator for Values<'a, V> {}
+
+/// A mutable iterator over the values of a `Tree` in key order.
+pub struct ValuesMut<'a, V>(pub(crate) IterMut<'a, V>);
+
+impl<'a, V> Iterator for ValuesMut<'a, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, val)| val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, V> ExactSizeIterator for ValuesMut<'a, V> {}
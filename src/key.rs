@@ -1,3 +1,4 @@
+use crate::FromArtKey;
 use std::borrow::Borrow;
 use std::fmt;
 use std::hash::{Hash, Hasher};
@@ -36,7 +37,7 @@ impl Key {
     fn new_medium(slice: &[u8]) -> Self {
         debug_assert!(slice.len() == MEDIUM_LEN);
         let mut v = [0; MEDIUM_LEN];
-        v[..].copy_from_slice(&slice[..]);
+        v.copy_from_slice(slice);
         Key::Medium(v)
     }
 
@@ -58,6 +59,11 @@ impl Key {
         }
     }
 
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     #[inline]
     pub fn as_slice(&self) -> &[u8] {
         match self {
@@ -81,8 +87,8 @@ impl Key {
                 let new_b = v.into_boxed_slice();
                 Key::Large(new_b)
             }
-            Key::Small(len, b) if *len < 15 => {
-                let mut b2 = b.clone();
+            Key::Small(len, b) if *len < SMALL_LIMIT_U8 => {
+                let mut b2 = *b;
                 b2[*len as usize] = byte;
                 Key::Small(*len + 1, b2)
             }
@@ -101,6 +107,26 @@ impl Key {
             _ => unreachable!(),
         }
     }
+
+    /// Decodes the key into the type it was encoded from with `ArtKey`.
+    pub fn decode<K: FromArtKey>(&self) -> Option<K> {
+        K::decode(self.as_slice())
+    }
+
+    pub fn concat_slice(&self, bytes: &[u8]) -> Key {
+        let len = self.len() + bytes.len();
+        if len <= MEDIUM_LEN {
+            // fits inline, no need for a heap buffer.
+            let mut v = [0; MEDIUM_LEN];
+            v[..self.len()].copy_from_slice(self.as_slice());
+            v[self.len()..len].copy_from_slice(bytes);
+            return Key::from(&v[..len]);
+        }
+        let mut v = Vec::with_capacity(len);
+        v.extend_from_slice(self.as_slice());
+        v.extend_from_slice(bytes);
+        Key::from(v)
+    }
 }
 
 impl PartialEq for Key {
@@ -111,7 +137,7 @@ impl PartialEq for Key {
 
 impl PartialOrd for Key {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.as_slice().partial_cmp(other.as_slice())
+        Some(self.cmp(other))
     }
 }
 
@@ -169,10 +195,9 @@ impl From<Vec<u8>> for Key {
     fn from(v: Vec<u8>) -> Key {
         match v.len() {
             0 => Key::Empty,
-            l if l < SMALL_LIMIT => Key::new_small(&v[..]),
+            l if l <= SMALL_LIMIT => Key::new_small(&v[..]),
             MEDIUM_LEN => Key::new_medium(&v[..]),
-            l if l > MEDIUM_LEN => Key::Large(v.into_boxed_slice()),
-            _ => unreachable!(),
+            _ => Key::Large(v.into_boxed_slice()),
         }
     }
 }
@@ -276,6 +301,18 @@ impl From<&usize> for Key {
     }
 }
 
+#[test]
+fn test_key_concat_grows_through_every_variant() {
+    let mut key = Key::Empty;
+    let mut expected = vec![];
+    for byte in 0..40u8 {
+        key = key.concat(byte);
+        expected.push(byte);
+        assert_eq!(key.as_slice(), &expected[..]);
+        assert_eq!(key, Key::from(expected.clone()));
+    }
+}
+
 // #[test]
 // fn test_key_as_slice_works_for_empty() {
 //     assert_eq!(Key::Empty.as_slice(), &b""[..]);
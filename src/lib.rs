@@ -1,20 +1,20 @@
-#![feature(portable_simd)]
+#![cfg_attr(feature = "nightly-simd", feature(portable_simd))]
 // #![feature(specialization)]
 
 mod node;
 use node::{BoxNode, Node, Seek};
 
 mod node4;
-use node4::{Node4, Node4IntoIter, Node4Iter};
+use node4::{Node4, Node4IntoIter, Node4Iter, Node4IterMut};
 
 mod node16;
-use node16::{Node16, Node16IntoIter, Node16Iter};
+use node16::{Node16, Node16IntoIter, Node16Iter, Node16IterMut};
 
 mod node48;
-use node48::{Node48, Node48IntoIter, Node48Iter};
+use node48::{Node48, Node48IntoIter, Node48Iter, Node48IterMut};
 
 mod node256;
-use node256::{Node256, Node256IntoIter, Node256Iter};
+use node256::{Node256, Node256IntoIter, Node256Iter, Node256IterMut};
 
 mod leaf;
 use leaf::Leaf;
@@ -33,6 +33,55 @@ pub mod describe;
 mod tree;
 pub use tree::Tree;
 
+mod iter;
+pub use iter::{IntoIter, Iter, IterMut, Keys, PrefixIter, Range, Values, ValuesMut};
+
+mod cursor;
+pub use cursor::{Cursor, CursorMut};
+
+mod entry;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+
+mod art_key;
+pub use art_key::{ArtKey, FromArtKey};
+
+mod tuple_key;
+pub use tuple_key::{FromKeyComponent, KeyComponent};
+
+mod typed_tree;
+pub use typed_tree::{Decoded, TypedTree};
+
+mod bulk;
+
+mod concurrent;
+pub use concurrent::ConcurrentTree;
+
+mod persistent;
+pub use persistent::{PersistentIter, PersistentTree};
+
+mod automaton;
+pub use automaton::{Automaton, PatternError, Search};
+
+mod glob;
+pub use glob::Glob;
+
+mod regex;
+pub use regex::Regex;
+
+mod fuzzy;
+
+mod ip_table;
+pub use ip_table::{IpNet, IpTable, ParseIpNetError};
+
+mod stats;
+pub use stats::TreeStats;
+
+mod snapshot;
+pub use snapshot::{ArtKeyCodec, ValueCodec};
+
+#[cfg(feature = "serde")]
+mod serde_impl;
+
 // mod art;
 // pub use art::Art;
 
@@ -1,5 +1,8 @@
 use crate::describe::{Describe, Describer};
-use crate::{Branch, Leaf, Node16, Node256, Node4, Node48};
+use crate::{Branch, Key, Leaf, Node16, Node256, Node4, Node48};
+use crate::{Node16IntoIter, Node256IntoIter, Node48IntoIter, Node4IntoIter};
+use crate::{Node16Iter, Node256Iter, Node48Iter, Node4Iter};
+use crate::{Node16IterMut, Node256IterMut, Node48IterMut, Node4IterMut};
 
 /// SeekKey is a fast, efficient whole-key struct that:
 /// 1) keeps track of the index that the key is currently on (for finding the next node)
@@ -13,16 +16,23 @@ pub(crate) struct Seek<'a> {
 }
 
 impl<'a> Seek<'a> {
-    pub fn new(key: &'a [u8]) -> Seek {
+    pub fn at(key: &'a [u8], idx: usize) -> Seek<'a> {
         Seek {
-            byte: key[0],
-            idx: 0,
+            byte: key[idx],
+            idx,
             key,
         }
     }
+
+    /// The rest of the key, starting at the current byte.
+    pub fn tail(&self) -> &'a [u8] {
+        &self.key[self.idx..]
+    }
 }
 
+#[derive(Default)]
 pub(crate) enum Node<V> {
+    #[default]
     None,
     Branch(Box<Branch<V>>),
     Leaf(Leaf<V>),
@@ -49,6 +59,7 @@ where
 
 impl<V> Describer for Node<V> {
     fn describe(&self, d: &mut Describe) {
+        d.indent();
         match self {
             Node::None => d.push_str("Node::None\n"),
             Node::Leaf(leaf) => {
@@ -56,7 +67,6 @@ impl<V> Describer for Node<V> {
                 leaf.describe(d);
             }
             Node::BoxNode(box_node) => {
-                d.indent();
                 d.push_str("Node::BoxNode => ");
                 box_node.describe(d);
             }
@@ -65,7 +75,7 @@ impl<V> Describer for Node<V> {
                 box_node.describe(d);
             }
             Node::Branch(branch) => {
-                d.push_str("Node::Branch =>");
+                d.push_str("Node::Branch => ");
                 branch.as_ref().describe(d);
             }
         }
@@ -82,6 +92,57 @@ impl<V> Node<V> {
         }
     }
 
+    /// Follows `key` one step down from this node, returning the next node and the
+    /// depth it sits at.
+    ///
+    /// A step is a single child byte for an inner node or the whole sequence of a
+    /// Branch. `depth` must be less than the length of the key.
+    pub fn next_node<'a>(&'a self, key: &[u8], depth: usize) -> Option<(&'a Node<V>, usize)> {
+        let seek = Seek::at(key, depth);
+        match self {
+            Node::Branch(branch) => {
+                let seq = branch.sequence();
+                if seek.tail().starts_with(seq) {
+                    Some((&branch.node, depth + seq.len()))
+                } else {
+                    None
+                }
+            }
+            _ => self.find_child(seek).map(|child| (child, depth + 1)),
+        }
+    }
+
+    /// Puts `sequence` in front of `node`, merging it into `node` if that is a Branch.
+    pub fn with_prefix(sequence: &[u8], node: Node<V>) -> Node<V> {
+        if sequence.is_empty() {
+            return node;
+        }
+        match node {
+            Node::Branch(mut branch) => {
+                branch.sequence = Key::from(sequence).concat_slice(&branch.sequence);
+                Node::Branch(branch)
+            }
+            node => Node::Branch(Box::new(Branch::new(sequence, node))),
+        }
+    }
+
+    /// Splits this Branch in front of its byte at `at`, turning that byte into the
+    /// first child of a new Node4.
+    ///
+    /// The bytes before `at` stay compressed in a Branch in front of the Node4.
+    pub fn split_branch(&mut self, at: usize) {
+        let branch = match std::mem::take(self) {
+            Node::Branch(branch) => branch,
+            _ => unreachable!(),
+        };
+        let Branch { sequence, node } = *branch;
+        let seek = Seek::at(&sequence, at);
+        let mut node4 = Node4::new();
+        let _ = node4.add_child(seek, Node::with_prefix(&sequence[at + 1..], node));
+        let inner = Node::BoxNode(BoxNode::Node4(Box::new(node4)));
+        *self = Node::with_prefix(&sequence[..at], inner);
+    }
+
     // pub fn get_leaf_mut(&mut self) -> Option<&mut Leaf<V>> {
     //     match self {
     //         Node::Leaf(leaf) | Node::BoxNodeLeaf(_, leaf) => Some(leaf),
@@ -107,7 +168,11 @@ impl<V> Node<V> {
                 None
             }
             Node::BoxNodeLeaf(_, leaf) => Some(leaf.insert(val)),
-            Node::Branch(_) => unreachable!(),
+            Node::Branch(_) => {
+                // the key ends in front of the Branch.
+                self.split_branch(0);
+                self.insert_in_leaf(val)
+            }
         }
     }
 
@@ -122,18 +187,154 @@ impl<V> Node<V> {
     //     }
     // }
 
-    pub fn find_child_mut<'a>(&mut self, seek: Seek<'a>) -> Option<&mut Node<V>> {
-        unsafe { std::mem::transmute(self.find_child(seek)) }
+    pub fn find_child_mut(&mut self, seek: Seek<'_>) -> Option<&mut Node<V>> {
+        match self {
+            Node::None | Node::Leaf(_) => None,
+            Node::BoxNode(bn) => bn.find_child_mut(seek),
+            Node::BoxNodeLeaf(bn, _) => bn.find_child_mut(seek),
+            Node::Branch(_) => unreachable!(),
+        }
     }
 
-    pub fn is_none(&self) -> bool {
+    /// The mutable counterpart of `next_node`.
+    pub fn next_node_mut<'a>(
+        &'a mut self,
+        key: &[u8],
+        depth: usize,
+    ) -> Option<(&'a mut Node<V>, usize)> {
+        let seek = Seek::at(key, depth);
+        match self {
+            Node::Branch(branch) => {
+                if seek.tail().starts_with(branch.sequence()) {
+                    let depth = depth + branch.sequence.len();
+                    Some((&mut branch.node, depth))
+                } else {
+                    None
+                }
+            }
+            _ => self.find_child_mut(seek).map(|child| (child, depth + 1)),
+        }
+    }
+
+    /// Follows `key` down from this node for as long as the tree has a path for it.
+    ///
+    /// Returns the deepest node reached with the number of key bytes consumed to
+    /// reach it. When that is the whole key the node is where the key's value lives.
+    pub fn descend_mut(&mut self, key: &[u8]) -> (&mut Node<V>, usize) {
+        let mut node = self;
+        let mut depth = 0;
+        // each step is checked before it is taken. Trying one that turns out to be a
+        // dead end would leave `node` mutably borrowed, so it could not be returned.
+        while depth < key.len() && node.next_node(key, depth).is_some() {
+            (node, depth) = node.next_node_mut(key, depth).unwrap();
+        }
+        (node, depth)
+    }
+
+    /// Follows `key` down from this node as far as the tree has a path for it,
+    /// returning the number of steps taken.
+    ///
+    /// When the key's value is at the end of the path, also returns how many of
+    /// those steps lead to the highest node that removing the value restructures.
+    /// That is the deepest node on the path that keeps other values, or the Branch
+    /// in front of it, since the nodes below it only hold the value and are removed
+    /// with it. Nothing above that node changes.
+    pub fn trace(&self, key: &[u8]) -> (usize, Option<usize>) {
+        let mut node = self;
+        let mut depth = 0;
+        let mut steps = 0;
+        let mut anchor = 0;
+        let mut below_branch = false;
+        loop {
+            let keeps_others = match node {
+                Node::BoxNodeLeaf(..) => true,
+                Node::BoxNode(bn) => bn.count() > 1,
+                _ => false,
+            };
+            if keeps_others {
+                anchor = steps - below_branch as usize;
+            }
+            if depth == key.len() {
+                break;
+            }
+            match node.next_node(key, depth) {
+                Some((next, next_depth)) => {
+                    below_branch = matches!(node, Node::Branch(_));
+                    (node, depth) = (next, next_depth);
+                    steps += 1;
+                }
+                None => break,
+            }
+        }
+        let found = depth == key.len() && node.leaf().is_some();
+        (steps, found.then_some(anchor))
+    }
+
+    /// Takes `steps` steps of `key` down from this node, on a path that `trace`
+    /// found, returning the node reached and the depth it sits at.
+    pub fn walk_mut(&mut self, key: &[u8], steps: usize) -> (&mut Node<V>, usize) {
+        let mut node = self;
+        let mut depth = 0;
+        for _ in 0..steps {
+            (node, depth) = node.next_node_mut(key, depth).unwrap();
+        }
+        (node, depth)
+    }
+
+    /// Inserts a new value for `key` into the subtree of this node, returning a
+    /// reference to the stored value.
+    ///
+    /// This node must be the one `descend_mut` stopped at after consuming `depth`
+    /// bytes of a key that is not in the tree yet.
+    pub fn insert_below(&mut self, key: &[u8], depth: usize, val: V) -> &mut V {
+        if depth == key.len() {
+            let _ = self.insert_in_leaf(val);
+            return &mut self.leaf_mut().unwrap().val;
+        }
+        if let Node::Branch(branch) = self {
+            // the key leaves the sequence partway, so a Node4 is needed where the
+            // two paths diverge.
+            let common = branch.common_prefix_len(&key[depth..]);
+            self.split_branch(common);
+            return match self {
+                Node::Branch(branch) => branch.node.insert_below(key, depth + common, val),
+                node => node.insert_below(key, depth, val),
+            };
+        }
+        // nothing else shares the rest of the key, so it goes in a single Branch.
+        let leaf = Node::Leaf(Leaf::new(val));
+        let child = if self.is_none() {
+            *self = Node::with_prefix(&key[depth..], leaf);
+            self
+        } else {
+            let seek = Seek::at(key, depth);
+            self.add_child(seek, Node::with_prefix(&key[depth + 1..], leaf))
+        };
+        let node = match child {
+            Node::Branch(branch) => &mut branch.node,
+            node => node,
+        };
+        &mut node.leaf_mut().unwrap().val
+    }
+
+    pub fn leaf(&self) -> Option<&Leaf<V>> {
+        match self {
+            Node::Leaf(leaf) | Node::BoxNodeLeaf(_, leaf) => Some(leaf),
+            _ => None,
+        }
+    }
+
+    pub fn leaf_mut(&mut self) -> Option<&mut Leaf<V>> {
         match self {
-            Node::None => true,
-            Node::BoxNode(BoxNode::None) => true,
-            _ => false,
+            Node::Leaf(leaf) | Node::BoxNodeLeaf(_, leaf) => Some(leaf),
+            _ => None,
         }
     }
 
+    pub fn is_none(&self) -> bool {
+        matches!(self, Node::None | Node::BoxNode(BoxNode::None))
+    }
+
     // pub fn is_leaf(&self) -> bool {
     //     todo!()
     // }
@@ -187,8 +388,163 @@ impl<V> Node<V> {
         }
     }
 
+    /// Removes the value stored under `key` from the subtree rooted at this node.
+    ///
+    /// `depth` is the number of key bytes that have already been consumed to reach
+    /// this node. Children left empty by the removal are detached and every node on
+    /// the way back up is shrunk if it dropped below its occupancy threshold.
+    pub fn remove(&mut self, key: &[u8], depth: usize) -> Option<V> {
+        if let Node::Branch(branch) = self {
+            if !key[depth..].starts_with(branch.sequence()) {
+                return None;
+            }
+            let depth = depth + branch.sequence.len();
+            let val = branch.node.remove(key, depth)?;
+            self.compress_branch();
+            return Some(val);
+        }
+        if depth == key.len() {
+            let leaf = self.take_leaf()?;
+            self.shrink();
+            return Some(leaf.val);
+        }
+        let seek = Seek::at(key, depth);
+        let child = self.find_child_mut(seek)?;
+        let val = child.remove(key, depth + 1)?;
+        if child.is_none() {
+            let _ = self.remove_child(seek);
+        }
+        Some(val)
+    }
+
+    /// Removes every value whose key starts with `prefix` from the subtree rooted at
+    /// this node, returning how many values were removed.
+    pub fn remove_prefix(&mut self, prefix: &[u8], depth: usize) -> usize {
+        if let Node::Branch(branch) = self {
+            let rest = &prefix[depth..];
+            if branch.sequence.starts_with(rest) {
+                // the prefix ends within the sequence, so the whole Branch matches.
+                let removed = self.count_values();
+                *self = Node::None;
+                return removed;
+            }
+            if !rest.starts_with(branch.sequence()) {
+                return 0;
+            }
+            let depth = depth + branch.sequence.len();
+            let removed = branch.node.remove_prefix(prefix, depth);
+            self.compress_branch();
+            return removed;
+        }
+        if depth == prefix.len() {
+            let removed = self.count_values();
+            *self = Node::None;
+            return removed;
+        }
+        let seek = Seek::at(prefix, depth);
+        let child = match self.find_child_mut(seek) {
+            Some(child) => child,
+            None => return 0,
+        };
+        let removed = child.remove_prefix(prefix, depth + 1);
+        if child.is_none() {
+            let _ = self.remove_child(seek);
+        }
+        removed
+    }
+
+    /// The number of values stored in the subtree rooted at this node.
+    pub fn count_values(&self) -> usize {
+        match self {
+            Node::None => 0,
+            Node::Leaf(_) => 1,
+            Node::BoxNode(bn) => bn.iter().map(|(_, child)| child.count_values()).sum(),
+            Node::BoxNodeLeaf(bn, _) => {
+                1 + bn
+                    .iter()
+                    .map(|(_, child)| child.count_values())
+                    .sum::<usize>()
+            }
+            Node::Branch(branch) => branch.node.count_values(),
+        }
+    }
+
+    /// Restores the Branch invariants after a removal below this Branch.
+    ///
+    /// A Branch whose node was emptied disappears and a Branch whose node collapsed
+    /// into another Branch absorbs that Branch's sequence.
+    fn compress_branch(&mut self) {
+        let branch = match self {
+            Node::Branch(branch) => branch,
+            _ => return,
+        };
+        if branch.node.is_none() {
+            *self = Node::None;
+        } else if let Node::Branch(_) = branch.node {
+            let Branch { sequence, node } = match std::mem::take(self) {
+                Node::Branch(branch) => *branch,
+                _ => unreachable!(),
+            };
+            *self = Node::with_prefix(&sequence, node);
+        }
+    }
+
+    /// Takes the leaf out of this node, leaving the node's children in place.
+    pub fn take_leaf(&mut self) -> Option<Leaf<V>> {
+        match self {
+            Node::Leaf(_) => match std::mem::take(self) {
+                Node::Leaf(leaf) => Some(leaf),
+                _ => unreachable!(),
+            },
+            Node::BoxNodeLeaf(_, _) => match std::mem::take(self) {
+                Node::BoxNodeLeaf(box_node, leaf) => {
+                    *self = Node::BoxNode(box_node);
+                    Some(leaf)
+                }
+                _ => unreachable!(),
+            },
+            _ => None,
+        }
+    }
+
+    /// Detaches the child at `seek.byte` and shrinks this node if needed.
+    ///
+    /// The child must exist, although it may already have been emptied in place.
+    pub fn remove_child(&mut self, seek: Seek<'_>) -> Option<Node<V>> {
+        let child = match self {
+            Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => bn.remove_child(seek)?,
+            _ => return None,
+        };
+        self.shrink();
+        Some(child)
+    }
+
+    /// Shrinks the inner node to the next smaller node type once it is underfull.
+    ///
+    /// A node without any children collapses entirely: a `BoxNode` becomes
+    /// `Node::None` and a `BoxNodeLeaf` becomes a plain `Node::Leaf`. A `BoxNode`
+    /// with a single child has nothing branching off of it, so it is compressed
+    /// into a Branch in front of that child.
     pub fn shrink(&mut self) {
-        todo!()
+        match self {
+            Node::BoxNode(bn) if bn.is_empty() => {
+                let _ = std::mem::take(self);
+            }
+            Node::BoxNode(bn) if bn.count() == 1 => {
+                let bn = std::mem::replace(bn, BoxNode::None);
+                let (byte, child) = bn.into_iter().next().unwrap();
+                *self = Node::with_prefix(&[byte], child);
+            }
+            Node::BoxNodeLeaf(bn, _) if bn.is_empty() => {
+                let leaf = match std::mem::take(self) {
+                    Node::BoxNodeLeaf(_, leaf) => leaf,
+                    _ => unreachable!(),
+                };
+                *self = Node::Leaf(leaf);
+            }
+            Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => bn.shrink_if_underfull(),
+            Node::None | Node::Leaf(_) | Node::Branch(_) => (),
+        }
     }
 
     pub fn add_child(&mut self, seek: Seek<'_>, child: Node<V>) -> &mut Node<V> {
@@ -225,12 +581,6 @@ impl<V> Node<V> {
     //         }
 }
 
-impl<V> Default for Node<V> {
-    fn default() -> Self {
-        Node::None
-    }
-}
-
 pub(crate) enum BoxNode<V> {
     // None is a temporary value used for replacing a boxed node, making changes to it, and putting it
     // back.
@@ -291,6 +641,63 @@ impl<V> BoxNode<V> {
         }
     }
 
+    /// Builds an inner node with room for `capacity` children out of `children`.
+    ///
+    /// `capacity` is 4, 16, 48 or 256 and picks the node type. The children must
+    /// be in byte order and fit in the node.
+    pub fn with_children(capacity: usize, children: Vec<(u8, Node<V>)>) -> BoxNode<V> {
+        debug_assert!(children.len() <= capacity);
+        let count = children.len();
+        match capacity {
+            4 => {
+                let mut node4 = Node4::new();
+                for (i, (byte, child)) in children.into_iter().enumerate() {
+                    node4.key[i] = byte;
+                    node4.children[i] = child;
+                }
+                node4.count = count as u8;
+                BoxNode::Node4(Box::new(node4))
+            }
+            16 => {
+                let mut node16 = Node16::new();
+                for (i, (byte, child)) in children.into_iter().enumerate() {
+                    node16.key[i] = byte;
+                    node16.children[i] = child;
+                }
+                node16.count = count as u8;
+                BoxNode::Node16(Box::new(node16))
+            }
+            48 => {
+                let mut node48 = Node48::new();
+                for (i, (byte, child)) in children.into_iter().enumerate() {
+                    node48.children_index[byte as usize] = (i + 1) as u8;
+                    node48.children[i] = child;
+                }
+                node48.count = count as u8;
+                BoxNode::Node48(Box::new(node48))
+            }
+            256 => {
+                let mut node256 = Node256::new();
+                for (byte, child) in children {
+                    node256.children[byte as usize] = child;
+                }
+                node256.count = count as u16;
+                BoxNode::Node256(Box::new(node256))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn find_child_mut(&mut self, seek: Seek<'_>) -> Option<&mut Node<V>> {
+        match self {
+            BoxNode::Node4(node4) => node4.find_child_mut(seek),
+            BoxNode::Node16(node16) => node16.find_child_mut(seek),
+            BoxNode::Node48(node48) => node48.find_child_mut(seek),
+            BoxNode::Node256(node256) => node256.find_child_mut(seek),
+            _ => unreachable!(),
+        }
+    }
+
     pub fn is_full(&self) -> bool {
         match self {
             BoxNode::Node4(n) => n.is_full(),
@@ -302,9 +709,42 @@ impl<V> BoxNode<V> {
     }
 
     pub fn is_none(&self) -> bool {
+        matches!(self, BoxNode::None)
+    }
+
+    /// The number of children held by the inner node.
+    pub fn count(&self) -> usize {
         match self {
-            BoxNode::None => true,
-            _ => false,
+            BoxNode::Node4(n) => n.count as usize,
+            BoxNode::Node16(n) => n.count as usize,
+            BoxNode::Node48(n) => n.count as usize,
+            BoxNode::Node256(n) => n.count as usize,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+
+    pub fn is_underfull(&self) -> bool {
+        match self {
+            // an empty Node4 is collapsed by Node::shrink rather than shrunk.
+            BoxNode::Node4(_) => false,
+            BoxNode::Node16(n) => n.is_underfull(),
+            BoxNode::Node48(n) => n.is_underfull(),
+            BoxNode::Node256(n) => n.is_underfull(),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn remove_child(&mut self, seek: Seek<'_>) -> Option<Node<V>> {
+        match self {
+            BoxNode::Node4(n) => n.remove_child(seek),
+            BoxNode::Node16(n) => n.remove_child(seek),
+            BoxNode::Node48(n) => n.remove_child(seek),
+            BoxNode::Node256(n) => n.remove_child(seek),
+            _ => unreachable!(),
         }
     }
 
@@ -318,6 +758,61 @@ impl<V> BoxNode<V> {
         }
     }
 
+    /// The first child with a byte after `after`, or the first child for `None`.
+    pub fn next_child(&self, after: Option<u8>) -> Option<(u8, &Node<V>)> {
+        match self {
+            BoxNode::Node4(n) => n.next_child(after),
+            BoxNode::Node16(n) => n.next_child(after),
+            BoxNode::Node48(n) => n.next_child(after),
+            BoxNode::Node256(n) => n.next_child(after),
+            _ => unreachable!(),
+        }
+    }
+
+    /// The last child with a byte before `before`, or the last child for `None`.
+    pub fn prev_child(&self, before: Option<u8>) -> Option<(u8, &Node<V>)> {
+        match self {
+            BoxNode::Node4(n) => n.prev_child(before),
+            BoxNode::Node16(n) => n.prev_child(before),
+            BoxNode::Node48(n) => n.prev_child(before),
+            BoxNode::Node256(n) => n.prev_child(before),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Iterates the children in byte order.
+    pub fn iter(&self) -> BoxNodeIter<'_, V> {
+        match self {
+            BoxNode::Node4(n) => BoxNodeIter::Node4(n.iter()),
+            BoxNode::Node16(n) => BoxNodeIter::Node16(n.iter()),
+            BoxNode::Node48(n) => BoxNodeIter::Node48(n.iter()),
+            BoxNode::Node256(n) => BoxNodeIter::Node256(n.iter()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Iterates the children mutably in byte order.
+    pub fn iter_mut(&mut self) -> BoxNodeIterMut<'_, V> {
+        match self {
+            BoxNode::Node4(n) => BoxNodeIterMut::Node4(n.iter_mut()),
+            BoxNode::Node16(n) => BoxNodeIterMut::Node16(n.iter_mut()),
+            BoxNode::Node48(n) => BoxNodeIterMut::Node48(n.iter_mut()),
+            BoxNode::Node256(n) => BoxNodeIterMut::Node256(n.iter_mut()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Consumes the node, yielding the children in byte order.
+    pub fn into_iter(self) -> BoxNodeIntoIter<V> {
+        match self {
+            BoxNode::Node4(n) => BoxNodeIntoIter::Node4(n.into_iter()),
+            BoxNode::Node16(n) => BoxNodeIntoIter::Node16(n.into_iter()),
+            BoxNode::Node48(n) => BoxNodeIntoIter::Node48(n.into_iter()),
+            BoxNode::Node256(n) => BoxNodeIntoIter::Node256(n.into_iter()),
+            _ => unreachable!(),
+        }
+    }
+
     pub fn grow_if_full(&mut self) {
         if self.is_full() {
             self.grow()
@@ -347,6 +842,118 @@ impl<V> BoxNode<V> {
             _ => unreachable!(),
         }
     }
+
+    pub fn shrink_if_underfull(&mut self) {
+        if self.is_underfull() {
+            self.shrink()
+        }
+    }
+
+    pub fn shrink(&mut self) {
+        // take ownership.
+        let owned_self = std::mem::replace(self, BoxNode::None);
+        match owned_self {
+            bn4 @ BoxNode::Node4(_) => {
+                let none = std::mem::replace(self, bn4);
+                assert!(none.is_none());
+            }
+            BoxNode::Node16(node16) => {
+                let none = std::mem::replace(self, BoxNode::Node4(Box::new(node16.shrink())));
+                assert!(none.is_none());
+            }
+            BoxNode::Node48(node48) => {
+                let none = std::mem::replace(self, BoxNode::Node16(Box::new(node48.shrink())));
+                assert!(none.is_none());
+            }
+            BoxNode::Node256(node256) => {
+                let none = std::mem::replace(self, BoxNode::Node48(Box::new(node256.shrink())));
+                assert!(none.is_none());
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+pub(crate) enum BoxNodeIter<'a, V> {
+    Node4(Node4Iter<'a, V>),
+    Node16(Node16Iter<'a, V>),
+    Node48(Node48Iter<'a, V>),
+    Node256(Node256Iter<'a, V>),
+}
+
+impl<'a, V> Iterator for BoxNodeIter<'a, V> {
+    type Item = (u8, &'a Node<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            BoxNodeIter::Node4(it) => it.next(),
+            BoxNodeIter::Node16(it) => it.next(),
+            BoxNodeIter::Node48(it) => it.next(),
+            BoxNodeIter::Node256(it) => it.next(),
+        }
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for BoxNodeIter<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            BoxNodeIter::Node4(it) => it.next_back(),
+            BoxNodeIter::Node16(it) => it.next_back(),
+            BoxNodeIter::Node48(it) => it.next_back(),
+            BoxNodeIter::Node256(it) => it.next_back(),
+        }
+    }
+}
+
+pub(crate) enum BoxNodeIterMut<'a, V> {
+    Node4(Node4IterMut<'a, V>),
+    Node16(Node16IterMut<'a, V>),
+    Node48(Node48IterMut<'a, V>),
+    Node256(Node256IterMut<'a, V>),
+}
+
+impl<'a, V> Iterator for BoxNodeIterMut<'a, V> {
+    type Item = (u8, &'a mut Node<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            BoxNodeIterMut::Node4(it) => it.next(),
+            BoxNodeIterMut::Node16(it) => it.next(),
+            BoxNodeIterMut::Node48(it) => it.next(),
+            BoxNodeIterMut::Node256(it) => it.next(),
+        }
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for BoxNodeIterMut<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            BoxNodeIterMut::Node4(it) => it.next_back(),
+            BoxNodeIterMut::Node16(it) => it.next_back(),
+            BoxNodeIterMut::Node48(it) => it.next_back(),
+            BoxNodeIterMut::Node256(it) => it.next_back(),
+        }
+    }
+}
+
+pub(crate) enum BoxNodeIntoIter<V> {
+    Node4(Node4IntoIter<V>),
+    Node16(Node16IntoIter<V>),
+    Node48(Node48IntoIter<V>),
+    Node256(Node256IntoIter<V>),
+}
+
+impl<V> Iterator for BoxNodeIntoIter<V> {
+    type Item = (u8, Node<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            BoxNodeIntoIter::Node4(it) => it.next(),
+            BoxNodeIntoIter::Node16(it) => it.next(),
+            BoxNodeIntoIter::Node48(it) => it.next(),
+            BoxNodeIntoIter::Node256(it) => it.next(),
+        }
+    }
 }
 
 // fn is_leaf<V>(node: &Node<V>) -> bool {
@@ -1,5 +1,5 @@
 use crate::describe::{Describe, Describer};
-use crate::{util, Node, Node48, Seek};
+use crate::{util, Node, Node4, Node48, Seek};
 
 pub(crate) struct Node16<V> {
     // pub prefix: Vec<u8>,
@@ -50,27 +50,26 @@ impl<V> Node16<V> {
         self.count == 16
     }
 
+    pub fn is_underfull(&self) -> bool {
+        self.count <= 3
+    }
+
     pub fn find_child(&self, seek: Seek<'_>) -> Option<&Node<V>> {
-        // TODO: simd
-
-        // if node.type==Node16 // SSE comparison
-        // key=_mm_set1_epi8(byte)
-        // cmp=_mm_cmpeq_epi8(key, node.key)
-        // mask=(1<<node.count)-1
-        // bitfield=_mm_movemask_epi8(cmp)&mask
-        // if bitfield
-        // return node.child[ctz(bitfield)]
-        // else
-        // return NULL
-
-        match (&self.key[..self.count as usize]).binary_search_by(|probe| probe.cmp(&seek.byte)) {
+        match self.search(seek.byte) {
             Ok(index) => Some(&self.children[index]),
             Err(_) => None,
         }
     }
 
+    pub fn find_child_mut(&mut self, seek: Seek<'_>) -> Option<&mut Node<V>> {
+        match self.search(seek.byte) {
+            Ok(index) => Some(&mut self.children[index]),
+            Err(_) => None,
+        }
+    }
+
     pub fn add_child(&mut self, seek: Seek<'_>, child: Node<V>) -> &mut Node<V> {
-        match (&self.key[..self.count as usize]).binary_search_by(|probe| probe.cmp(&seek.byte)) {
+        match self.search(seek.byte) {
             Ok(_) => {
                 panic!("Node16::add_child: child already exists {seek:?}");
             }
@@ -84,6 +83,51 @@ impl<V> Node16<V> {
         }
     }
 
+    pub fn remove_child(&mut self, seek: Seek<'_>) -> Option<Node<V>> {
+        let index = self.search(seek.byte).ok()?;
+        let child = std::mem::take(&mut self.children[index]);
+        self.move_items_left_of(index);
+        self.count -= 1;
+        Some(child)
+    }
+
+    /// Finds `byte` in the keys, with the same result as a binary search: `Ok` with
+    /// the index of the key or `Err` with the index it would be inserted at.
+    ///
+    /// This is the SSE lookup from the ART paper. All 16 keys are compared against
+    /// the byte at once and the masks of the used keys give both the match and,
+    /// because the keys are sorted, the insert position.
+    #[cfg(feature = "nightly-simd")]
+    fn search(&self, byte: u8) -> Result<usize, usize> {
+        use std::simd::cmp::{SimdPartialEq, SimdPartialOrd};
+        use std::simd::u8x16;
+
+        let keys = u8x16::from_array(self.key);
+        let needle = u8x16::splat(byte);
+        let used = (1u64 << self.count) - 1;
+        let found = keys.simd_eq(needle).to_bitmask() & used;
+        if found != 0 {
+            return Ok(found.trailing_zeros() as usize);
+        }
+        let less = keys.simd_lt(needle).to_bitmask() & used;
+        Err(less.count_ones() as usize)
+    }
+
+    /// Finds `byte` in the keys with a binary search, used unless the
+    /// `nightly-simd` feature is enabled.
+    #[cfg(not(feature = "nightly-simd"))]
+    fn search(&self, byte: u8) -> Result<usize, usize> {
+        self.key[..self.count as usize].binary_search(&byte)
+    }
+
+    fn move_items_left_of(&mut self, index: usize) {
+        // start at the removed index and move the hole rightward
+        for i in index..(self.count as usize - 1) {
+            util::swap_unchecked(&mut self.key, i, i + 1);
+            util::swap_unchecked(&mut self.children, i, i + 1);
+        }
+    }
+
     fn move_items_right_of(&mut self, index: usize) {
         // .rev() - start at the rightmost i and move leftward
         for i in (index..self.count as usize).rev() {
@@ -96,7 +140,7 @@ impl<V> Node16<V> {
         debug_assert!(self.count == 16);
         let mut node48 = Node48::new();
         // std::mem::swap(&mut self.prefix, &mut node48.prefix);
-        for (i, k) in (&self.key[..self.count as usize]).into_iter().enumerate() {
+        for (i, k) in self.key[..self.count as usize].iter().enumerate() {
             node48.children_index[*k as usize] = (i + 1) as u8;
             std::mem::swap(&mut node48.children[i], &mut self.children[i]);
             node48.count = self.count;
@@ -104,23 +148,88 @@ impl<V> Node16<V> {
         node48
     }
 
-    fn iter(&self) -> Node16Iter<V> {
+    pub fn shrink(self: Box<Self>) -> Node4<V> {
+        debug_assert!(self.count <= 4);
+        let mut node4 = Node4::new();
+        for (i, (byte, child)) in self.into_iter().enumerate() {
+            node4.key[i] = byte;
+            node4.children[i] = child;
+            node4.count += 1;
+        }
+        node4
+    }
+
+    /// The first child with a byte after `after`, or the first child for `None`.
+    pub fn next_child(&self, after: Option<u8>) -> Option<(u8, &Node<V>)> {
+        let index = match after.map(|byte| self.search(byte)) {
+            None => 0,
+            Some(Ok(i)) => i + 1,
+            Some(Err(i)) => i,
+        };
+        (index < self.count as usize).then(|| (self.key[index], &self.children[index]))
+    }
+
+    /// The last child with a byte before `before`, or the last child for `None`.
+    pub fn prev_child(&self, before: Option<u8>) -> Option<(u8, &Node<V>)> {
+        let index = match before.map(|byte| self.search(byte)) {
+            None => self.count as usize,
+            Some(Ok(i)) | Some(Err(i)) => i,
+        };
+        let index = index.checked_sub(1)?;
+        Some((self.key[index], &self.children[index]))
+    }
+
+    pub fn iter(&self) -> Node16Iter<'_, V> {
         Node16Iter::new(self)
     }
+
+    pub fn iter_mut(&mut self) -> Node16IterMut<'_, V> {
+        Node16IterMut::new(self)
+    }
+
+    pub fn into_iter(self: Box<Self>) -> Node16IntoIter<V> {
+        Node16IntoIter::new(self)
+    }
 }
 
 pub(crate) struct Node16IntoIter<V> {
-    node16: Node16<V>,
+    node16: Box<Node16<V>>,
     index: usize,
 }
+
+impl<V> Node16IntoIter<V> {
+    pub fn new(node16: Box<Node16<V>>) -> Node16IntoIter<V> {
+        Node16IntoIter { node16, index: 0 }
+    }
+}
+
+impl<V> Iterator for Node16IntoIter<V> {
+    type Item = (u8, Node<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.node16.count as usize {
+            return None;
+        }
+        let idx = self.node16.key[self.index];
+        let child = std::mem::take(&mut self.node16.children[self.index]);
+        self.index += 1;
+        Some((idx, child))
+    }
+}
+
 pub(crate) struct Node16Iter<'a, V> {
     node16: &'a Node16<V>,
     index: usize,
+    end: usize,
 }
 
 impl<'a, V> Node16Iter<'a, V> {
     pub(crate) fn new(node16: &'a Node16<V>) -> Self {
-        Self { node16, index: 0 }
+        Self {
+            node16,
+            index: 0,
+            end: node16.count as usize,
+        }
     }
 }
 
@@ -128,7 +237,7 @@ impl<'a, V> Iterator for Node16Iter<'a, V> {
     type Item = (u8, &'a Node<V>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index == self.node16.count as usize {
+        if self.index == self.end {
             return None;
         }
         let idx = self.node16.key[self.index];
@@ -137,3 +246,67 @@ impl<'a, V> Iterator for Node16Iter<'a, V> {
         Some((idx, child))
     }
 }
+
+impl<'a, V> DoubleEndedIterator for Node16Iter<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.index == self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some((self.node16.key[self.end], &self.node16.children[self.end]))
+    }
+}
+
+pub(crate) struct Node16IterMut<'a, V> {
+    key: &'a [u8],
+    children: std::slice::IterMut<'a, Node<V>>,
+}
+
+impl<'a, V> Node16IterMut<'a, V> {
+    pub(crate) fn new(node16: &'a mut Node16<V>) -> Self {
+        let count = node16.count as usize;
+        Self {
+            key: &node16.key[..count],
+            children: node16.children[..count].iter_mut(),
+        }
+    }
+}
+
+impl<'a, V> Iterator for Node16IterMut<'a, V> {
+    type Item = (u8, &'a mut Node<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let child = self.children.next()?;
+        let (idx, rest) = self.key.split_first().unwrap();
+        self.key = rest;
+        Some((*idx, child))
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Node16IterMut<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let child = self.children.next_back()?;
+        let (idx, rest) = self.key.split_last().unwrap();
+        self.key = rest;
+        Some((*idx, child))
+    }
+}
+
+#[test]
+fn test_node16_search_matches_binary_search() {
+    let mut node16 = Node16::<()>::new();
+    let keys: Vec<u8> = (0..16).map(|i| i * 13 + 7).collect();
+    for count in 0..=16 {
+        node16.key[..count].copy_from_slice(&keys[..count]);
+        node16.count = count as u8;
+        for byte in 0..=255u8 {
+            assert_eq!(
+                node16.search(byte),
+                keys[..count].binary_search(&byte),
+                "count: {}, byte: {}",
+                count,
+                byte
+            );
+        }
+    }
+}
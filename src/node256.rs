@@ -1,5 +1,5 @@
 use crate::describe::{Describe, Describer};
-use crate::{Node, Seek};
+use crate::{Node, Node48, Seek};
 
 pub(crate) struct Node256<V> {
     // pub prefix: Vec<u8>,
@@ -22,7 +22,15 @@ where
 
 impl<V> Describer for Node256<V> {
     fn describe(&self, d: &mut Describe) {
-        d.push_str("Node256")
+        d.push_str("Node256\n");
+        d.nest(|d| {
+            for (byte, child) in self.iter() {
+                d.indent();
+                d.push_str(&format!("{:?} => ", byte));
+                d.eat_next_indent();
+                child.describe(d);
+            }
+        })
     }
 }
 
@@ -35,7 +43,17 @@ impl<V> Node256<V> {
         }
     }
     pub fn find_child(&self, seek: Seek<'_>) -> Option<&Node<V>> {
-        self.children.get(seek.byte as usize)
+        match &self.children[seek.byte as usize] {
+            child if child.is_none() => None,
+            child => Some(child),
+        }
+    }
+
+    pub fn find_child_mut(&mut self, seek: Seek<'_>) -> Option<&mut Node<V>> {
+        match &mut self.children[seek.byte as usize] {
+            child if child.is_none() => None,
+            child => Some(child),
+        }
     }
 
     pub fn is_full(&self) -> bool {
@@ -43,6 +61,10 @@ impl<V> Node256<V> {
         self.count == 256
     }
 
+    pub fn is_underfull(&self) -> bool {
+        self.count <= 37
+    }
+
     pub fn add_child(&mut self, seek: Seek<'_>, child: Node<V>) -> &mut Node<V> {
         debug_assert!(self.count <= 256);
         debug_assert!(self.children[seek.byte as usize].is_none());
@@ -51,16 +73,66 @@ impl<V> Node256<V> {
         *out = child;
         out
     }
+
+    /// Clears the slot for `seek.byte`.
+    ///
+    /// The slot may already have been emptied in place by a removal further down the
+    /// tree, so the caller must only remove bytes that were known to have a child.
+    pub fn remove_child(&mut self, seek: Seek<'_>) -> Option<Node<V>> {
+        debug_assert!(self.count > 0);
+        self.count -= 1;
+        Some(std::mem::take(&mut self.children[seek.byte as usize]))
+    }
+
+    pub fn shrink(self: Box<Self>) -> Node48<V> {
+        debug_assert!(self.count <= 48);
+        let mut node48 = Node48::new();
+        for (i, (byte, child)) in self.into_iter().enumerate() {
+            node48.children_index[byte as usize] = (i + 1) as u8;
+            node48.children[i] = child;
+            node48.count += 1;
+        }
+        node48
+    }
+
+    /// The first child with a byte after `after`, or the first child for `None`.
+    pub fn next_child(&self, after: Option<u8>) -> Option<(u8, &Node<V>)> {
+        let start = after.map_or(0, |byte| byte as usize + 1);
+        (start..256)
+            .find(|byte| !self.children[*byte].is_none())
+            .map(|byte| (byte as u8, &self.children[byte]))
+    }
+
+    /// The last child with a byte before `before`, or the last child for `None`.
+    pub fn prev_child(&self, before: Option<u8>) -> Option<(u8, &Node<V>)> {
+        let end = before.map_or(256, |byte| byte as usize);
+        (0..end)
+            .rev()
+            .find(|byte| !self.children[*byte].is_none())
+            .map(|byte| (byte as u8, &self.children[byte]))
+    }
+
+    pub fn iter(&self) -> Node256Iter<'_, V> {
+        Node256Iter::new(self)
+    }
+
+    pub fn iter_mut(&mut self) -> Node256IterMut<'_, V> {
+        Node256IterMut::new(self)
+    }
+
+    pub fn into_iter(self: Box<Self>) -> Node256IntoIter<V> {
+        Node256IntoIter::new(self)
+    }
 }
 
 pub(crate) struct Node256IntoIter<V> {
-    node4: Node256<V>,
+    node256: Box<Node256<V>>,
     index: usize,
 }
 
 impl<V> Node256IntoIter<V> {
-    pub fn new(node4: Node256<V>) -> Node256IntoIter<V> {
-        Node256IntoIter { node4, index: 0 }
+    pub fn new(node256: Box<Node256<V>>) -> Node256IntoIter<V> {
+        Node256IntoIter { node256, index: 0 }
     }
 }
 
@@ -68,25 +140,31 @@ impl<V> Iterator for Node256IntoIter<V> {
     type Item = (u8, Node<V>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        todo!()
-        // if self.index == self.node4.count {
-        //     return None;
-        // }
-        // let idx = self.node4.key[self.index];
-        // let child = std::mem::replace(&mut self.node4.children[self.index], Node::Nothing);
-        // self.index += 1;
-        // Some((idx, child))
+        while self.index < 256 {
+            let byte = self.index;
+            self.index += 1;
+            let child = std::mem::take(&mut self.node256.children[byte]);
+            if !child.is_none() {
+                return Some((byte as u8, child));
+            }
+        }
+        None
     }
 }
 
 pub(crate) struct Node256Iter<'a, V> {
     node256: &'a Node256<V>,
     index: usize,
+    end: usize,
 }
 
 impl<'a, V> Node256Iter<'a, V> {
     pub fn new(node256: &'a Node256<V>) -> Node256Iter<'a, V> {
-        Node256Iter { node256, index: 0 }
+        Node256Iter {
+            node256,
+            index: 0,
+            end: 256,
+        }
     }
 }
 
@@ -94,14 +172,58 @@ impl<'a, V> Iterator for Node256Iter<'a, V> {
     type Item = (u8, &'a Node<V>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        todo!()
-        // if self.index == self.node4.count {
-        //     return None;
-        // }
-        // let idx = self.node4.key[self.index];
-        // let child = &self.node4.children[self.index];
-        // self.index += 1;
-        // Some((idx, child))
+        while self.index < self.end {
+            let byte = self.index;
+            self.index += 1;
+            let child = &self.node256.children[byte];
+            if !child.is_none() {
+                return Some((byte as u8, child));
+            }
+        }
+        None
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Node256Iter<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.index < self.end {
+            self.end -= 1;
+            let child = &self.node256.children[self.end];
+            if !child.is_none() {
+                return Some((self.end as u8, child));
+            }
+        }
+        None
+    }
+}
+
+pub(crate) struct Node256IterMut<'a, V> {
+    children: std::iter::Enumerate<std::slice::IterMut<'a, Node<V>>>,
+}
+
+impl<'a, V> Node256IterMut<'a, V> {
+    pub fn new(node256: &'a mut Node256<V>) -> Node256IterMut<'a, V> {
+        Node256IterMut {
+            children: node256.children.iter_mut().enumerate(),
+        }
+    }
+}
+
+impl<'a, V> Iterator for Node256IterMut<'a, V> {
+    type Item = (u8, &'a mut Node<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.children
+            .find(|(_, child)| !child.is_none())
+            .map(|(byte, child)| (byte as u8, child))
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Node256IterMut<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.children
+            .rfind(|(_, child)| !child.is_none())
+            .map(|(byte, child)| (byte as u8, child))
     }
 }
 
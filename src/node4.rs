@@ -1,5 +1,5 @@
 use crate::describe::{Describe, Describer};
-use crate::{Node, Node16, Seek};
+use crate::{util, Node, Node16, Seek};
 
 // TODO: look into simd
 pub(crate) struct Node4<V> {
@@ -60,14 +60,46 @@ impl<V> Node4<V> {
         None
     }
 
+    pub fn find_child_mut(&mut self, seek: Seek<'_>) -> Option<&mut Node<V>> {
+        let index = self.key[..self.count as usize]
+            .iter()
+            .position(|byte| *byte == seek.byte)?;
+        self.children.get_mut(index)
+    }
+
     pub fn add_child(&mut self, seek: Seek<'_>, child: Node<V>) -> &mut Node<V> {
-        debug_assert!(self.is_full() == false);
+        debug_assert!(!self.is_full());
         debug_assert!(self.find_child(seek).is_none());
-        self.key[self.count as usize] = seek.byte;
-        self.children[self.count as usize] = child;
-        let child_mut = &mut self.children[self.count as usize];
+        // keep the keys sorted so that iteration is in byte order.
+        let count = self.count as usize;
+        let index = self.key[..count]
+            .iter()
+            .position(|byte| *byte > seek.byte)
+            .unwrap_or(count);
+        for i in (index..count).rev() {
+            self.key[i + 1] = self.key[i];
+            util::swap_unchecked(&mut self.children, i, i + 1);
+        }
+        self.key[index] = seek.byte;
+        self.children[index] = child;
         self.count += 1;
-        child_mut
+        &mut self.children[index]
+    }
+
+    pub fn remove_child(&mut self, seek: Seek<'_>) -> Option<Node<V>> {
+        let count = self.count as usize;
+        let index = self.key[..count]
+            .iter()
+            .position(|byte| *byte == seek.byte)?;
+        let child = std::mem::take(&mut self.children[index]);
+        // shift the remaining children left to keep them contiguous.
+        for i in index..count - 1 {
+            self.key[i] = self.key[i + 1];
+            util::swap_unchecked(&mut self.children, i, i + 1);
+        }
+        self.key[count - 1] = 0;
+        self.count -= 1;
+        Some(child)
     }
 
     // pub fn set_prefix_len(&mut self, len: usize) {
@@ -78,29 +110,59 @@ impl<V> Node4<V> {
         let mut node16 = Node16::<V>::new();
         // node16.prefix = self.prefix;
         node16.count = self.count;
-        (&mut node16.key[..self.count as usize])
-            .swap_with_slice(&mut self.key[..self.count as usize]);
-        (&mut node16.children[..self.count as usize])
+        node16.key[..self.count as usize].swap_with_slice(&mut self.key[..self.count as usize]);
+        node16.children[..self.count as usize]
             .swap_with_slice(&mut self.children[..self.count as usize]);
         node16
     }
 
-    pub fn iter(&self) -> Node4Iter<V> {
+    /// The first child with a byte after `after`, or the first child for `None`.
+    pub fn next_child(&self, after: Option<u8>) -> Option<(u8, &Node<V>)> {
+        let count = self.count as usize;
+        let index = match after {
+            None => 0,
+            Some(byte) => self.key[..count]
+                .iter()
+                .position(|b| *b > byte)
+                .unwrap_or(count),
+        };
+        (index < count).then(|| (self.key[index], &self.children[index]))
+    }
+
+    /// The last child with a byte before `before`, or the last child for `None`.
+    pub fn prev_child(&self, before: Option<u8>) -> Option<(u8, &Node<V>)> {
+        let count = self.count as usize;
+        let index = match before {
+            None => count,
+            Some(byte) => self.key[..count]
+                .iter()
+                .position(|b| *b >= byte)
+                .unwrap_or(count),
+        };
+        let index = index.checked_sub(1)?;
+        Some((self.key[index], &self.children[index]))
+    }
+
+    pub fn iter(&self) -> Node4Iter<'_, V> {
         Node4Iter::<V>::new(self)
     }
 
-    pub fn into_iter(self) -> Node4IntoIter<V> {
+    pub fn iter_mut(&mut self) -> Node4IterMut<'_, V> {
+        Node4IterMut::<V>::new(self)
+    }
+
+    pub fn into_iter(self: Box<Self>) -> Node4IntoIter<V> {
         Node4IntoIter::<V>::new(self)
     }
 }
 
 pub(crate) struct Node4IntoIter<V> {
-    node4: Node4<V>,
+    node4: Box<Node4<V>>,
     index: usize,
 }
 
 impl<V> Node4IntoIter<V> {
-    pub fn new(node4: Node4<V>) -> Node4IntoIter<V> {
+    pub fn new(node4: Box<Node4<V>>) -> Node4IntoIter<V> {
         Node4IntoIter { node4, index: 0 }
     }
 }
@@ -122,11 +184,16 @@ impl<V> Iterator for Node4IntoIter<V> {
 pub(crate) struct Node4Iter<'a, V> {
     node4: &'a Node4<V>,
     index: usize,
+    end: usize,
 }
 
 impl<'a, V> Node4Iter<'a, V> {
     pub fn new(node4: &'a Node4<V>) -> Node4Iter<'a, V> {
-        Node4Iter { node4, index: 0 }
+        Node4Iter {
+            node4,
+            index: 0,
+            end: node4.count as usize,
+        }
     }
 }
 
@@ -134,7 +201,7 @@ impl<'a, V> Iterator for Node4Iter<'a, V> {
     type Item = (u8, &'a Node<V>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index >= self.node4.count as usize {
+        if self.index >= self.end {
             return None;
         }
         let idx = self.node4.key[self.index];
@@ -143,3 +210,48 @@ impl<'a, V> Iterator for Node4Iter<'a, V> {
         Some((idx, child))
     }
 }
+
+impl<'a, V> DoubleEndedIterator for Node4Iter<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some((self.node4.key[self.end], &self.node4.children[self.end]))
+    }
+}
+
+pub(crate) struct Node4IterMut<'a, V> {
+    key: &'a [u8],
+    children: std::slice::IterMut<'a, Node<V>>,
+}
+
+impl<'a, V> Node4IterMut<'a, V> {
+    pub fn new(node4: &'a mut Node4<V>) -> Node4IterMut<'a, V> {
+        let count = node4.count as usize;
+        Node4IterMut {
+            key: &node4.key[..count],
+            children: node4.children[..count].iter_mut(),
+        }
+    }
+}
+
+impl<'a, V> Iterator for Node4IterMut<'a, V> {
+    type Item = (u8, &'a mut Node<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let child = self.children.next()?;
+        let (idx, rest) = self.key.split_first().unwrap();
+        self.key = rest;
+        Some((*idx, child))
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Node4IterMut<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let child = self.children.next_back()?;
+        let (idx, rest) = self.key.split_last().unwrap();
+        self.key = rest;
+        Some((*idx, child))
+    }
+}
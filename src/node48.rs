@@ -1,5 +1,6 @@
 use crate::describe::{Describe, Describer};
-use crate::{Node, Node256, Seek};
+use crate::{Node, Node16, Node256, Seek};
+use std::marker::PhantomData;
 
 pub(crate) struct Node48<V> {
     // pub prefix: Vec<u8>,
@@ -24,7 +25,15 @@ where
 
 impl<V> Describer for Node48<V> {
     fn describe(&self, d: &mut Describe) {
-        d.push_str("Node48")
+        d.push_str("Node48\n");
+        d.nest(|d| {
+            for (byte, child) in self.iter() {
+                d.indent();
+                d.push_str(&format!("{:?} => ", byte));
+                d.eat_next_indent();
+                child.describe(d);
+            }
+        })
     }
 }
 
@@ -44,38 +53,114 @@ impl<V> Node48<V> {
         }
     }
 
+    pub fn find_child_mut(&mut self, seek: Seek<'_>) -> Option<&mut Node<V>> {
+        match self.children_index[seek.byte as usize] {
+            0 => None,
+            i => self.children.get_mut((i - 1) as usize),
+        }
+    }
+
     pub fn is_full(&self) -> bool {
         debug_assert!(self.count <= 48);
         self.count == 48
     }
 
-    pub fn grow(mut self) -> Node256<V> {
+    pub fn is_underfull(&self) -> bool {
+        self.count <= 12
+    }
+
+    pub fn grow(self: Box<Self>) -> Node256<V> {
         let mut node256 = Node256::new();
-        for byte_plus_one in (&self.children_index[..self.count as usize]).iter() {
-            let byte: usize = *byte_plus_one as usize - 1;
-            std::mem::swap(&mut node256.children[byte], &mut self.children[byte]);
-        }
         node256.count = self.count as u16;
+        for (byte, child) in self.into_iter() {
+            node256.children[byte as usize] = child;
+        }
         node256
     }
 
+    pub fn shrink(self: Box<Self>) -> Node16<V> {
+        debug_assert!(self.count <= 16);
+        let mut node16 = Node16::new();
+        // into_iter walks the bytes in order so the Node16 keys stay sorted.
+        for (i, (byte, child)) in self.into_iter().enumerate() {
+            node16.key[i] = byte;
+            node16.children[i] = child;
+            node16.count += 1;
+        }
+        node16
+    }
+
     pub fn add_child(&mut self, seek: Seek<'_>, child: Node<V>) -> &mut Node<V> {
         let i = self.count;
         self.count += 1;
-        self.children_index[seek.byte as usize] = (i + 1) as u8;
+        self.children_index[seek.byte as usize] = i + 1;
         self.children[i as usize] = child;
         &mut self.children[i as usize]
     }
+
+    pub fn remove_child(&mut self, seek: Seek<'_>) -> Option<Node<V>> {
+        let slot = match self.children_index[seek.byte as usize] {
+            0 => return None,
+            i => (i - 1) as usize,
+        };
+        self.children_index[seek.byte as usize] = 0;
+        let child = std::mem::take(&mut self.children[slot]);
+        // keep children[..count] contiguous by moving the last child into the hole.
+        let last = self.count as usize - 1;
+        if slot != last {
+            self.children.swap(slot, last);
+            let moved = self
+                .children_index
+                .iter()
+                .position(|i| *i as usize == last + 1)
+                .unwrap();
+            self.children_index[moved] = (slot + 1) as u8;
+        }
+        self.count -= 1;
+        Some(child)
+    }
+
+    /// The first child with a byte after `after`, or the first child for `None`.
+    pub fn next_child(&self, after: Option<u8>) -> Option<(u8, &Node<V>)> {
+        let start = after.map_or(0, |byte| byte as usize + 1);
+        (start..256).find_map(|byte| match self.children_index[byte] {
+            0 => None,
+            i => Some((byte as u8, &self.children[(i - 1) as usize])),
+        })
+    }
+
+    /// The last child with a byte before `before`, or the last child for `None`.
+    pub fn prev_child(&self, before: Option<u8>) -> Option<(u8, &Node<V>)> {
+        let end = before.map_or(256, |byte| byte as usize);
+        (0..end)
+            .rev()
+            .find_map(|byte| match self.children_index[byte] {
+                0 => None,
+                i => Some((byte as u8, &self.children[(i - 1) as usize])),
+            })
+    }
+
+    pub fn iter(&self) -> Node48Iter<'_, V> {
+        Node48Iter::new(self)
+    }
+
+    pub fn iter_mut(&mut self) -> Node48IterMut<'_, V> {
+        Node48IterMut::new(self)
+    }
+
+    pub fn into_iter(self: Box<Self>) -> Node48IntoIter<V> {
+        Node48IntoIter::new(self)
+    }
 }
 
 pub(crate) struct Node48IntoIter<V> {
-    node4: Node48<V>,
+    node48: Box<Node48<V>>,
     index: usize,
 }
 
 impl<V> Node48IntoIter<V> {
-    pub fn new(node4: Node48<V>) -> Node48IntoIter<V> {
-        Node48IntoIter { node4, index: 0 }
+    pub fn new(node48: Box<Node48<V>>) -> Node48IntoIter<V> {
+        Node48IntoIter { node48, index: 0 }
     }
 }
 
@@ -83,25 +168,34 @@ impl<V> Iterator for Node48IntoIter<V> {
     type Item = (u8, Node<V>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        todo!()
-        // if self.index == self.node4.count {
-        //     return None;
-        // }
-        // let idx = self.node4.key[self.index];
-        // let child = std::mem::replace(&mut self.node4.children[self.index], Node::Nothing);
-        // self.index += 1;
-        // Some((idx, child))
+        while self.index < 256 {
+            let byte = self.index;
+            self.index += 1;
+            match self.node48.children_index[byte] {
+                0 => continue,
+                i => {
+                    let child = std::mem::take(&mut self.node48.children[(i - 1) as usize]);
+                    return Some((byte as u8, child));
+                }
+            }
+        }
+        None
     }
 }
 
 pub(crate) struct Node48Iter<'a, V> {
     node48: &'a Node48<V>,
     index: usize,
+    end: usize,
 }
 
 impl<'a, V> Node48Iter<'a, V> {
     pub fn new(node48: &'a Node48<V>) -> Node48Iter<'a, V> {
-        Node48Iter { node48, index: 0 }
+        Node48Iter {
+            node48,
+            index: 0,
+            end: 256,
+        }
     }
 }
 
@@ -109,13 +203,83 @@ impl<'a, V> Iterator for Node48Iter<'a, V> {
     type Item = (u8, &'a Node<V>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        todo!()
-        // if self.index == self.node4.count {
-        //     return None;
-        // }
-        // let idx = self.node4.key[self.index];
-        // let child = &self.node4.children[self.index];
-        // self.index += 1;
-        // Some((idx, child))
+        while self.index < self.end {
+            let byte = self.index;
+            self.index += 1;
+            match self.node48.children_index[byte] {
+                0 => continue,
+                i => return Some((byte as u8, &self.node48.children[(i - 1) as usize])),
+            }
+        }
+        None
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Node48Iter<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.index < self.end {
+            self.end -= 1;
+            let byte = self.end;
+            match self.node48.children_index[byte] {
+                0 => continue,
+                i => return Some((byte as u8, &self.node48.children[(i - 1) as usize])),
+            }
+        }
+        None
+    }
+}
+
+pub(crate) struct Node48IterMut<'a, V> {
+    children_index: &'a [u8; 256],
+    // every byte maps to a distinct slot, so each child is handed out at most once.
+    children: *mut Node<V>,
+    index: usize,
+    end: usize,
+    _marker: PhantomData<&'a mut Node<V>>,
+}
+
+impl<'a, V> Node48IterMut<'a, V> {
+    pub fn new(node48: &'a mut Node48<V>) -> Node48IterMut<'a, V> {
+        Node48IterMut {
+            children: node48.children.as_mut_ptr(),
+            children_index: &node48.children_index,
+            index: 0,
+            end: 256,
+            _marker: PhantomData,
+        }
+    }
+
+    fn child(&self, i: u8) -> &'a mut Node<V> {
+        unsafe { &mut *self.children.add((i - 1) as usize) }
+    }
+}
+
+impl<'a, V> Iterator for Node48IterMut<'a, V> {
+    type Item = (u8, &'a mut Node<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.end {
+            let byte = self.index;
+            self.index += 1;
+            match self.children_index[byte] {
+                0 => continue,
+                i => return Some((byte as u8, self.child(i))),
+            }
+        }
+        None
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Node48IterMut<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.index < self.end {
+            self.end -= 1;
+            let byte = self.end;
+            match self.children_index[byte] {
+                0 => continue,
+                i => return Some((byte as u8, self.child(i))),
+            }
+        }
+        None
     }
 }
@@ -0,0 +1,333 @@
+use crate::Key;
+use std::sync::Arc;
+
+/// An immutable tree where every change makes a new version.
+///
+/// Nodes are shared through `Arc`, so `insert` and `remove` copy only the nodes on
+/// the path to the key and every other subtree is shared with the version they were
+/// called on. Old versions stay valid and cloning a version is one `Arc` clone.
+///
+/// ```
+/// let v1 = artoo::PersistentTree::new().insert(b"timeout", 30);
+/// let v2 = v1.insert(b"timeout", 60).insert(b"retries", 3);
+/// assert_eq!(v1.get(b"timeout"), Some(&30));
+/// assert_eq!(v2.get(b"timeout"), Some(&60));
+/// assert_eq!((v1.len(), v2.len()), (1, 2));
+/// ```
+pub struct PersistentTree<V> {
+    root: Option<Arc<PNode<V>>>,
+    count: usize,
+}
+
+/// A node of a `PersistentTree`: the compressed path leading to it, the value of
+/// the key that ends at it and its children sorted by byte.
+struct PNode<V> {
+    prefix: Key,
+    val: Option<Arc<V>>,
+    children: Vec<(u8, Arc<PNode<V>>)>,
+}
+
+impl<V> Clone for PNode<V> {
+    /// A shallow copy that shares the value and children.
+    fn clone(&self) -> Self {
+        PNode {
+            prefix: self.prefix.clone(),
+            val: self.val.clone(),
+            children: self.children.clone(),
+        }
+    }
+}
+
+impl<V> PNode<V> {
+    fn leaf(prefix: &[u8], val: Arc<V>) -> PNode<V> {
+        PNode {
+            prefix: Key::from(prefix),
+            val: Some(val),
+            children: Vec::new(),
+        }
+    }
+
+    fn child(&self, byte: u8) -> Result<usize, usize> {
+        self.children.binary_search_by_key(&byte, |(b, _)| *b)
+    }
+
+    /// Returns the copy of the path with `key` set to `val` and whether the key is new.
+    fn insert(&self, key: &[u8], val: Arc<V>) -> (PNode<V>, bool) {
+        let common = self
+            .prefix
+            .iter()
+            .zip(key)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if common < self.prefix.len() {
+            // the key leaves the prefix partway, so a new node takes the shared
+            // part and the old one moves below it with the rest.
+            let mut lower = self.clone();
+            lower.prefix = Key::from(&self.prefix[common + 1..]);
+            let mut split = PNode {
+                prefix: Key::from(&key[..common]),
+                val: None,
+                children: vec![(self.prefix[common], Arc::new(lower))],
+            };
+            match key[common..].split_first() {
+                None => split.val = Some(val),
+                Some((byte, rest)) => {
+                    let i = split.child(*byte).unwrap_err();
+                    split
+                        .children
+                        .insert(i, (*byte, Arc::new(PNode::leaf(rest, val))));
+                }
+            }
+            return (split, true);
+        }
+        let mut copy = self.clone();
+        let added = match key[common..].split_first() {
+            None => copy.val.replace(val).is_none(),
+            Some((byte, rest)) => match copy.child(*byte) {
+                Ok(i) => {
+                    let (child, added) = copy.children[i].1.insert(rest, val);
+                    copy.children[i].1 = Arc::new(child);
+                    added
+                }
+                Err(i) => {
+                    copy.children
+                        .insert(i, (*byte, Arc::new(PNode::leaf(rest, val))));
+                    true
+                }
+            },
+        };
+        (copy, added)
+    }
+
+    /// Returns `None` when `key` is missing, otherwise the copy of the path without
+    /// it, which is `None` itself when nothing is left.
+    fn remove(&self, key: &[u8]) -> Option<Option<PNode<V>>> {
+        let rest = key.strip_prefix(self.prefix.as_slice())?;
+        let mut copy = match rest.split_first() {
+            None => {
+                self.val.as_ref()?;
+                let mut copy = self.clone();
+                copy.val = None;
+                copy
+            }
+            Some((byte, rest)) => {
+                let i = self.child(*byte).ok()?;
+                let child = self.children[i].1.remove(rest)?;
+                let mut copy = self.clone();
+                match child {
+                    Some(child) => copy.children[i].1 = Arc::new(child),
+                    None => {
+                        copy.children.remove(i);
+                    }
+                }
+                copy
+            }
+        };
+        if copy.val.is_some() || copy.children.len() > 1 {
+            return Some(Some(copy));
+        }
+        // a node without a value and with a single child merges into the child,
+        // keeping paths compressed.
+        Some(copy.children.pop().map(|(byte, child)| {
+            let mut merged = (*child).clone();
+            merged.prefix = copy.prefix.concat(byte).concat_slice(&child.prefix);
+            merged
+        }))
+    }
+}
+
+impl<V> PersistentTree<V> {
+    pub fn new() -> PersistentTree<V> {
+        PersistentTree {
+            root: None,
+            count: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        let mut node = self.root.as_deref()?;
+        let mut key = key;
+        loop {
+            key = key.strip_prefix(node.prefix.as_slice())?;
+            match key.split_first() {
+                None => return node.val.as_deref(),
+                Some((byte, rest)) => {
+                    node = &node.children[node.child(*byte).ok()?].1;
+                    key = rest;
+                }
+            }
+        }
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns a new version with `key` set to `val`.
+    pub fn insert(&self, key: &[u8], val: V) -> PersistentTree<V> {
+        let val = Arc::new(val);
+        let (root, added) = match &self.root {
+            None => (PNode::leaf(key, val), true),
+            Some(root) => root.insert(key, val),
+        };
+        PersistentTree {
+            root: Some(Arc::new(root)),
+            count: self.count + added as usize,
+        }
+    }
+
+    /// Returns a new version without `key`. A missing key gives a clone of this one.
+    pub fn remove(&self, key: &[u8]) -> PersistentTree<V> {
+        let root = self.root.as_ref().and_then(|root| root.remove(key));
+        match root {
+            None => self.clone(),
+            Some(root) => PersistentTree {
+                root: root.map(Arc::new),
+                count: self.count - 1,
+            },
+        }
+    }
+
+    /// Iterates the entries in key order.
+    pub fn iter(&self) -> PersistentIter<'_, V> {
+        PersistentIter {
+            stack: self
+                .root
+                .iter()
+                .map(|root| (Key::from(""), &**root))
+                .collect(),
+        }
+    }
+}
+
+impl<V> Clone for PersistentTree<V> {
+    fn clone(&self) -> Self {
+        PersistentTree {
+            root: self.root.clone(),
+            count: self.count,
+        }
+    }
+}
+
+impl<V> Default for PersistentTree<V> {
+    fn default() -> Self {
+        PersistentTree::new()
+    }
+}
+
+impl<K: AsRef<[u8]>, V> FromIterator<(K, V)> for PersistentTree<V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> PersistentTree<V> {
+        iter.into_iter()
+            .fold(PersistentTree::new(), |tree, (key, val)| {
+                tree.insert(key.as_ref(), val)
+            })
+    }
+}
+
+impl<'a, V> IntoIterator for &'a PersistentTree<V> {
+    type Item = (Key, &'a V);
+    type IntoIter = PersistentIter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct PersistentIter<'a, V> {
+    /// Nodes still to visit, the next one on top, with the key leading up to them.
+    stack: Vec<(Key, &'a PNode<V>)>,
+}
+
+impl<'a, V> Iterator for PersistentIter<'a, V> {
+    type Item = (Key, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (key, node) = self.stack.pop()?;
+            let key = key.concat_slice(&node.prefix);
+            for (byte, child) in node.children.iter().rev() {
+                self.stack.push((key.concat(*byte), child));
+            }
+            if let Some(val) = &node.val {
+                return Some((key, val));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_persistent_tree_versions_are_independent() {
+    use std::collections::BTreeMap;
+
+    let mut versions = vec![(PersistentTree::new(), BTreeMap::new())];
+    let mut state = 0x1234_5678u32;
+    for _ in 0..3_000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let (tree, map) = &versions[state as usize % versions.len()];
+        let key = match state % 3 {
+            0 => (state % 500).to_be_bytes().to_vec(),
+            1 => format!("cfg/{}", state % 41).into_bytes(),
+            _ => format!("cfg/{}/x", state % 17).into_bytes(),
+        };
+        let mut map = map.clone();
+        let tree = match state % 4 {
+            0 => {
+                map.remove(&key);
+                tree.remove(&key)
+            }
+            _ => {
+                map.insert(key.clone(), state);
+                tree.insert(&key, state)
+            }
+        };
+        versions.push((tree, map));
+    }
+    // every version still holds exactly what it held when it was made.
+    for (tree, map) in &versions {
+        assert_eq!(tree.len(), map.len());
+        assert!(tree
+            .iter()
+            .map(|(k, v)| (k.to_vec(), *v))
+            .eq(map.iter().map(|(k, v)| (k.clone(), *v))));
+        for key in map.keys() {
+            assert_eq!(tree.get(key), map.get(key));
+        }
+    }
+}
+
+#[test]
+fn test_persistent_tree_shares_unchanged_subtrees() {
+    let base: PersistentTree<u32> = (0..4_096u32).map(|i| (i.to_be_bytes(), i)).collect();
+    let next = base.insert(&5u32.to_be_bytes(), 500);
+    let (a, b) = (base.root.as_ref().unwrap(), next.root.as_ref().unwrap());
+    assert!(!Arc::ptr_eq(a, b));
+    // only the children on the path to the key were copied.
+    let mut shared = 0;
+    let (a, b) = (&a.children[0].1, &b.children[0].1);
+    for ((_, x), (_, y)) in a.children.iter().zip(&b.children) {
+        shared += Arc::ptr_eq(x, y) as usize;
+    }
+    assert_eq!(shared, a.children.len() - 1);
+    assert_eq!(base.get(&5u32.to_be_bytes()), Some(&5));
+    assert_eq!(next.get(&5u32.to_be_bytes()), Some(&500));
+
+    // removing a missing key or everything leaves nothing behind.
+    let same = next.remove(b"missing");
+    assert!(Arc::ptr_eq(
+        same.root.as_ref().unwrap(),
+        next.root.as_ref().unwrap()
+    ));
+    let empty = (0..4_096u32).fold(next, |tree, i| tree.remove(&i.to_be_bytes()));
+    assert!(empty.is_empty() && empty.root.is_none());
+    assert_eq!(base.len(), 4_096);
+}
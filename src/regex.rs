@@ -0,0 +1,451 @@
+use crate::automaton::ByteSet;
+use crate::{Automaton, PatternError};
+use std::cell::RefCell;
+
+/// The most copies a counted repetition like `a{2,5}` may expand to.
+const MAX_REPEAT: u32 = 1_000;
+
+/// The most instructions a pattern may compile to. Nested counted repetitions
+/// multiply, so `MAX_REPEAT` alone does not bound the program.
+const MAX_PROGRAM: usize = 50_000;
+
+/// A parsed expression before it is compiled.
+enum Hir {
+    Empty,
+    Set(ByteSet),
+    Concat(Vec<Hir>),
+    Alt(Vec<Hir>),
+    Repeat {
+        hir: Box<Hir>,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+struct Parser<'p> {
+    pattern: &'p [u8],
+    pos: usize,
+}
+
+impl<'p> Parser<'p> {
+    fn peek(&self) -> Option<u8> {
+        self.pattern.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        self.pos += found as usize;
+        found
+    }
+
+    fn error(&self, reason: &'static str) -> PatternError {
+        PatternError::new(self.pos, reason)
+    }
+
+    fn alt(&mut self) -> Result<Hir, PatternError> {
+        let mut alts = vec![self.concat()?];
+        while self.eat(b'|') {
+            alts.push(self.concat()?);
+        }
+        Ok(match alts.len() {
+            1 => alts.pop().unwrap(),
+            _ => Hir::Alt(alts),
+        })
+    }
+
+    fn concat(&mut self) -> Result<Hir, PatternError> {
+        let mut items = vec![];
+        while let Some(byte) = self.peek() {
+            if byte == b'|' || byte == b')' {
+                break;
+            }
+            let atom = self.atom()?;
+            items.push(self.repeat(atom)?);
+        }
+        Ok(match items.len() {
+            0 => Hir::Empty,
+            1 => items.pop().unwrap(),
+            _ => Hir::Concat(items),
+        })
+    }
+
+    fn atom(&mut self) -> Result<Hir, PatternError> {
+        let byte = self.peek().unwrap();
+        let set = match byte {
+            b'(' => {
+                let open = self.pos;
+                self.pos += 1;
+                let hir = self.alt()?;
+                if !self.eat(b')') {
+                    return Err(PatternError::new(open, "unclosed group"));
+                }
+                return Ok(hir);
+            }
+            b'[' => {
+                let (set, end) = ByteSet::parse_class(self.pattern, self.pos, b"^")?;
+                self.pos = end;
+                return Ok(Hir::Set(set));
+            }
+            b'.' => ByteSet::full(),
+            b'\\' => {
+                self.pos += 1;
+                match self.peek() {
+                    None => return Err(self.error("trailing backslash")),
+                    Some(b'd') => class(&[(b'0', b'9')]),
+                    Some(b'w') => class(&[(b'0', b'9'), (b'A', b'Z'), (b'a', b'z'), (b'_', b'_')]),
+                    Some(b's') => class(&[(b'\t', b'\r'), (b' ', b' ')]),
+                    Some(b'n') => ByteSet::byte(b'\n'),
+                    Some(b't') => ByteSet::byte(b'\t'),
+                    Some(byte) if byte.is_ascii_alphanumeric() => {
+                        return Err(self.error("unknown escape"))
+                    }
+                    Some(byte) => ByteSet::byte(byte),
+                }
+            }
+            b'*' | b'+' | b'?' | b'{' => return Err(self.error("repetition of nothing")),
+            b'^' | b'$' => return Err(self.error("anchors are implied")),
+            byte => ByteSet::byte(byte),
+        };
+        self.pos += 1;
+        Ok(Hir::Set(set))
+    }
+
+    fn repeat(&mut self, mut hir: Hir) -> Result<Hir, PatternError> {
+        loop {
+            let start = self.pos;
+            let (min, max) = match self.peek() {
+                Some(b'*') => (0, None),
+                Some(b'+') => (1, None),
+                Some(b'?') => (0, Some(1)),
+                Some(b'{') => {
+                    self.pos += 1;
+                    let min = self.number()?;
+                    let max = match self.eat(b',') {
+                        false => Some(min),
+                        true if self.peek() == Some(b'}') => None,
+                        true => Some(self.number()?),
+                    };
+                    if self.peek() != Some(b'}') {
+                        return Err(self.error("unclosed counted repetition"));
+                    }
+                    if max.is_some_and(|max| max < min) || max.unwrap_or(min) > MAX_REPEAT {
+                        return Err(PatternError::new(start, "invalid counted repetition"));
+                    }
+                    (min, max)
+                }
+                _ => return Ok(hir),
+            };
+            self.pos += 1;
+            hir = Hir::Repeat {
+                hir: Box::new(hir),
+                min,
+                max,
+            };
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, PatternError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.pattern[start..self.pos])
+            .unwrap()
+            .parse()
+            .map_err(|_| PatternError::new(start, "invalid counted repetition"))
+    }
+}
+
+fn class(ranges: &[(u8, u8)]) -> ByteSet {
+    let mut set = ByteSet::empty();
+    for (lo, hi) in ranges {
+        set.insert_range(*lo, *hi);
+    }
+    set
+}
+
+/// One instruction of the compiled NFA.
+enum Inst {
+    /// Consumes a byte from the set and goes to the next instruction.
+    Byte(ByteSet, usize),
+    /// Goes to both instructions without consuming anything.
+    Split(usize, usize),
+    Match,
+}
+
+fn push(prog: &mut Vec<Inst>, inst: Inst) -> Option<usize> {
+    if prog.len() >= MAX_PROGRAM {
+        return None;
+    }
+    prog.push(inst);
+    Some(prog.len() - 1)
+}
+
+/// Compiles `hir` so that it continues to `next` once matched, returning its first
+/// instruction, or `None` once the program grows past `MAX_PROGRAM`. Building back
+/// to front means no jump has to be patched, apart from the one that closes a loop.
+fn compile(prog: &mut Vec<Inst>, hir: &Hir, next: usize) -> Option<usize> {
+    match hir {
+        Hir::Empty => Some(next),
+        Hir::Set(set) => push(prog, Inst::Byte(set.clone(), next)),
+        Hir::Concat(items) => items
+            .iter()
+            .rev()
+            .try_fold(next, |next, item| compile(prog, item, next)),
+        Hir::Alt(alts) => {
+            let mut start = compile(prog, alts.last().unwrap(), next)?;
+            for alt in alts.iter().rev().skip(1) {
+                let first = compile(prog, alt, next)?;
+                start = push(prog, Inst::Split(first, start))?;
+            }
+            Some(start)
+        }
+        Hir::Repeat { hir, min, max } => {
+            let mut start = match max {
+                None => {
+                    let split = push(prog, Inst::Split(next, next))?;
+                    let body = compile(prog, hir, split)?;
+                    prog[split] = Inst::Split(body, next);
+                    split
+                }
+                Some(max) => {
+                    let mut start = next;
+                    for _ in *min..*max {
+                        let body = compile(prog, hir, start)?;
+                        start = push(prog, Inst::Split(body, next))?;
+                    }
+                    start
+                }
+            };
+            for _ in 0..*min {
+                start = compile(prog, hir, start)?;
+            }
+            Some(start)
+        }
+    }
+}
+
+/// The buffers for following `Split`s, kept per thread so that `accept` does not
+/// allocate one sized to the program for every byte.
+#[derive(Default)]
+struct Scratch {
+    seen: Vec<bool>,
+    marked: Vec<usize>,
+    stack: Vec<usize>,
+}
+
+thread_local! {
+    static SCRATCH: RefCell<Scratch> = RefCell::default();
+}
+
+/// A regular expression over whole keys, as an `Automaton` for `Tree::search`.
+///
+/// The expression is matched against bytes rather than characters and must cover
+/// the whole key, as if it began with `^` and ended with `$`. It supports `|`,
+/// groups, `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`, `.` for any byte, classes like
+/// `[a-z]` and `[^:]`, the `\d`, `\w` and `\s` classes and `\` to escape a
+/// metacharacter. There are no captures, anchors or lazy repetitions.
+///
+/// ```
+/// use artoo::{Regex, Tree};
+///
+/// let mut tree = Tree::new();
+/// for key in ["order:17", "order:2024", "order:x9", "orders"] {
+///     tree.insert(key.as_bytes(), ());
+/// }
+/// let re = Regex::new(r"order:\d{2,4}").unwrap();
+/// let keys: Vec<_> = tree.search(&re).map(|(k, _)| k.to_vec()).collect();
+/// assert_eq!(keys, [b"order:17".to_vec(), b"order:2024".to_vec()]);
+/// ```
+pub struct Regex {
+    prog: Vec<Inst>,
+    start: usize,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, PatternError> {
+        let mut parser = Parser {
+            pattern: pattern.as_bytes(),
+            pos: 0,
+        };
+        let hir = parser.alt()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("unopened group"));
+        }
+        let mut prog = vec![Inst::Match];
+        let start = compile(&mut prog, &hir, 0).ok_or(PatternError::new(
+            0,
+            "pattern compiles to too many instructions",
+        ))?;
+        Ok(Regex { prog, start })
+    }
+
+    /// The state made of the instructions in `pcs` along with everything reachable
+    /// from them without consuming a byte.
+    fn enter(&self, pcs: impl Iterator<Item = usize>) -> Vec<usize> {
+        SCRATCH.with(|scratch| {
+            let Scratch {
+                seen,
+                marked,
+                stack,
+            } = &mut *scratch.borrow_mut();
+            if seen.len() < self.prog.len() {
+                seen.resize(self.prog.len(), false);
+            }
+            let mut state = vec![];
+            for pc in pcs {
+                stack.push(pc);
+                while let Some(pc) = stack.pop() {
+                    if std::mem::replace(&mut seen[pc], true) {
+                        continue;
+                    }
+                    marked.push(pc);
+                    match self.prog[pc] {
+                        Inst::Split(a, b) => stack.extend([b, a]),
+                        _ => state.push(pc),
+                    }
+                }
+            }
+            for pc in marked.drain(..) {
+                seen[pc] = false;
+            }
+            state
+        })
+    }
+}
+
+/// The state is the set of `Byte` and `Match` instructions the NFA is at, so an
+/// empty set is a dead end.
+impl Automaton for Regex {
+    type State = Vec<usize>;
+
+    fn start(&self) -> Vec<usize> {
+        self.enter(std::iter::once(self.start))
+    }
+
+    fn is_match(&self, state: &Vec<usize>) -> bool {
+        state.iter().any(|pc| matches!(self.prog[*pc], Inst::Match))
+    }
+
+    fn can_match(&self, state: &Vec<usize>) -> bool {
+        !state.is_empty()
+    }
+
+    fn accept(&self, state: &Vec<usize>, byte: u8) -> Vec<usize> {
+        self.enter(state.iter().filter_map(|pc| match &self.prog[*pc] {
+            Inst::Byte(set, to) if set.contains(byte) => Some(*to),
+            _ => None,
+        }))
+    }
+}
+
+#[cfg(test)]
+fn regex_matches(pattern: &str, key: &str) -> bool {
+    let re = Regex::new(pattern).unwrap();
+    let state = key
+        .bytes()
+        .fold(re.start(), |state, byte| re.accept(&state, byte));
+    re.is_match(&state)
+}
+
+#[test]
+fn test_regex_matches() {
+    for (pattern, key, expected) in [
+        ("", "", true),
+        ("", "a", false),
+        ("abc", "abc", true),
+        ("abc", "abcd", false),
+        ("a|bc", "bc", true),
+        ("a|bc", "ab", false),
+        ("(ab)*", "", true),
+        ("(ab)*", "abab", true),
+        ("(ab)*", "aba", false),
+        ("a+b?", "aaa", true),
+        ("a+b?", "b", false),
+        ("(a*)*b", "aab", true),
+        ("x{3}", "xxx", true),
+        ("x{3}", "xx", false),
+        ("x{2,}", "xxxxx", true),
+        ("x{1,2}", "xxx", false),
+        ("(a|b){0,2}c", "bac", true),
+        ("(a|b){0,2}c", "abac", false),
+        ("user:[^:]+:session", "user:42:session", true),
+        ("user:[^:]+:session", "user:4:2:session", false),
+        (r"\d\w\s", "7_ ", true),
+        (r"\d", "a", false),
+        (r"\.\*", ".*", true),
+        (r"a.c", "a\nc", true),
+    ] {
+        assert_eq!(
+            regex_matches(pattern, key),
+            expected,
+            "{:?} {:?}",
+            pattern,
+            key
+        );
+    }
+    for (pattern, pos) in [
+        ("(ab", 0),
+        ("ab)", 2),
+        ("*a", 0),
+        ("a{2", 3),
+        ("a{3,2}", 1),
+        ("a{1001}", 1),
+        (r"\q", 1),
+        ("^a", 0),
+        ("[a", 0),
+        ("((a?){1000}){1000}", 0),
+    ] {
+        assert_eq!(
+            Regex::new(pattern).err().map(|e| e.pos()),
+            Some(pos),
+            "{:?}",
+            pattern
+        );
+    }
+}
+
+#[test]
+fn test_regex_large_program() {
+    use crate::Tree;
+
+    // a long chain of optional bytes is one closure of thousands of instructions,
+    // which must not recurse once per instruction.
+    let re = Regex::new("((a?){100}){100}b").unwrap();
+    assert!(re.prog.len() > 20_000 && re.prog.len() <= MAX_PROGRAM);
+    let mut tree = Tree::new();
+    for key in ["b", "aab", "aaa", "ba"] {
+        tree.insert(key.as_bytes(), ());
+    }
+    let keys: Vec<Vec<u8>> = tree.search(&re).map(|(k, _)| k.to_vec()).collect();
+    assert_eq!(keys, [b"aab".to_vec(), b"b".to_vec()]);
+}
+
+#[test]
+fn test_regex_search_matches_filter() {
+    use crate::Tree;
+
+    let mut tree = Tree::new();
+    let mut state = 0x2f6b_1a3du32;
+    for _ in 0..3_000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let len = state as usize % 8;
+        let key: Vec<u8> = (0..len)
+            .map(|i| b"ab:1"[(state >> (i * 2 + 3)) as usize % 4])
+            .collect();
+        tree.insert(&key, state);
+    }
+    for pattern in ["a*", "(ab)+:1*", "[ab]{2}:.*", "b?(:|1)+a", ".*1", ""] {
+        let re = Regex::new(pattern).unwrap();
+        let found: Vec<(Vec<u8>, u32)> = tree.search(&re).map(|(k, v)| (k.to_vec(), *v)).collect();
+        let expected: Vec<(Vec<u8>, u32)> = tree
+            .iter()
+            .filter(|(k, _)| regex_matches(pattern, std::str::from_utf8(k).unwrap()))
+            .map(|(k, v)| (k.to_vec(), *v))
+            .collect();
+        assert!(!expected.is_empty() || pattern.is_empty(), "{:?}", pattern);
+        assert_eq!(found, expected, "{:?}", pattern);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{Key, Tree};
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Human readable formats only have string map keys, so there a key that is not
+/// UTF-8, or that could be mistaken for the escaped form, is written as `hex:`
+/// followed by its bytes in hex.
+const HEX_MARKER: &str = "hex:";
+
+/// A `Key` is a string when it is valid UTF-8, like its `Debug` output. Otherwise it
+/// is bytes, or its `hex:` form in human readable formats such as JSON.
+impl Serialize for Key {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = self.as_slice();
+        match std::str::from_utf8(bytes) {
+            Ok(s) if !(serializer.is_human_readable() && s.starts_with(HEX_MARKER)) => {
+                serializer.serialize_str(s)
+            }
+            _ if serializer.is_human_readable() => {
+                let mut hex = String::with_capacity(HEX_MARKER.len() + bytes.len() * 2);
+                hex.push_str(HEX_MARKER);
+                for byte in bytes {
+                    hex.push_str(&format!("{:02x}", byte));
+                }
+                serializer.serialize_str(&hex)
+            }
+            _ => serializer.serialize_bytes(bytes),
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+struct KeyVisitor {
+    /// Only human readable formats write the `hex:` form, elsewhere a string that
+    /// starts with `hex:` is that string.
+    human_readable: bool,
+}
+
+impl<'de> Visitor<'de> for KeyVisitor {
+    type Value = Key;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string, a `hex:` string or bytes")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Key, E> {
+        match v.strip_prefix(HEX_MARKER) {
+            Some(hex) if self.human_readable => decode_hex(hex)
+                .map(Key::from)
+                .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(v), &self)),
+            _ => Ok(Key::from(v)),
+        }
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Key, E> {
+        Ok(Key::from(v))
+    }
+
+    fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Key, E> {
+        Ok(Key::from(v))
+    }
+
+    // bytes written as a sequence, the way formats without a bytes type store them.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Key, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        Ok(Key::from(bytes))
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Key, D::Error> {
+        // self-describing formats pick the visit method from the data, the rest
+        // hand back whatever was serialized, which is a string or bytes.
+        let human_readable = deserializer.is_human_readable();
+        let visitor = KeyVisitor { human_readable };
+        if human_readable {
+            deserializer.deserialize_any(visitor)
+        } else {
+            deserializer.deserialize_bytes(visitor)
+        }
+    }
+}
+
+/// A `Tree` is a map from its keys to its values, in key order.
+impl<V: Serialize> Serialize for Tree<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, val) in self.iter() {
+            map.serialize_entry(&key, val)?;
+        }
+        map.end()
+    }
+}
+
+struct TreeVisitor<V>(PhantomData<fn() -> V>);
+
+impl<'de, V: Deserialize<'de>> Visitor<'de> for TreeVisitor<V> {
+    type Value = Tree<V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of keys to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Tree<V>, A::Error> {
+        let mut tree = Tree::new();
+        while let Some((key, val)) = map.next_entry::<Key, V>()? {
+            tree.insert(&key, val);
+        }
+        Ok(tree)
+    }
+}
+
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for Tree<V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Tree<V>, D::Error> {
+        deserializer.deserialize_map(TreeVisitor(PhantomData))
+    }
+}
+
+#[test]
+fn test_serde_key_is_a_string_when_utf8() {
+    let key = Key::from("hello");
+    assert_eq!(serde_json::to_string(&key).unwrap(), "\"hello\"");
+    let bytes = Key::from(&[0xff, 0x00][..]);
+    assert_eq!(serde_json::to_string(&bytes).unwrap(), "\"hex:ff00\"");
+    // a UTF-8 key that looks escaped is escaped itself.
+    let marked = Key::from("hex:00");
+    assert_eq!(
+        serde_json::to_string(&marked).unwrap(),
+        "\"hex:6865783a3030\""
+    );
+    assert!(serde_json::from_str::<Key>("\"hex:f\"").is_err());
+    assert!(serde_json::from_str::<Key>("\"hex:zz\"").is_err());
+    assert_eq!(
+        serde_json::from_str::<Key>("[255,0]").unwrap(),
+        Key::from(&[0xff, 0x00][..])
+    );
+    for key in [key, bytes, marked, Key::from(""), Key::from(&[7u8; 40][..])] {
+        let json = serde_json::to_string(&key).unwrap();
+        assert_eq!(serde_json::from_str::<Key>(&json).unwrap(), key);
+        let bin = bincode::serialize(&key).unwrap();
+        assert_eq!(bincode::deserialize::<Key>(&bin).unwrap(), key);
+    }
+}
+
+#[test]
+fn test_serde_tree_round_trips() {
+    let mut tree = Tree::<Vec<u32>>::new();
+    for word in ["b", "a", "abc", "", "ab", "zz"] {
+        tree.insert(word.as_bytes(), vec![word.len() as u32; word.len()]);
+    }
+    let json = serde_json::to_string(&tree).unwrap();
+    assert_eq!(
+        json,
+        r#"{"":[],"a":[1],"ab":[2,2],"abc":[3,3,3],"b":[1],"zz":[2,2]}"#
+    );
+    let from_json: Tree<Vec<u32>> = serde_json::from_str(&json).unwrap();
+    assert!(from_json.iter().eq(tree.iter()));
+
+    // binary keys are written as `hex:` strings, which JSON accepts as map keys.
+    for i in 0..1_000u32 {
+        tree.insert(&(i * 7919).to_be_bytes(), vec![i]);
+    }
+    let json = serde_json::to_string(&tree).unwrap();
+    assert!(json.contains(r#""hex:0000b99a":[6]"#));
+    let from_json: Tree<Vec<u32>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(from_json.len(), tree.len());
+    assert!(from_json.iter().eq(tree.iter()));
+
+    let bin = bincode::serialize(&tree).unwrap();
+    let from_bin: Tree<Vec<u32>> = bincode::deserialize(&bin).unwrap();
+    assert_eq!(from_bin.len(), tree.len());
+    assert!(from_bin.iter().eq(tree.iter()));
+}
+
+#[test]
+fn test_serde_keys_round_trip_in_self_describing_binary_formats() {
+    let mut tree = Tree::new();
+    for key in [&b"hex:00"[..], &[0x00], b"hex:", b"plain", &[0xff, 0x00]] {
+        tree.insert(key, key.len());
+    }
+    // MessagePack is self-describing but not human readable, so keys that start
+    // with `hex:` are written and read back as plain strings.
+    let msgpack = rmp_serde::to_vec(&tree).unwrap();
+    let from_msgpack: Tree<usize> = rmp_serde::from_slice(&msgpack).unwrap();
+    assert_eq!(from_msgpack.len(), tree.len());
+    assert!(from_msgpack.iter().eq(tree.iter()));
+    let marked = rmp_serde::to_vec(&Key::from("hex:00")).unwrap();
+    assert_eq!(marked, b"\xa6hex:00");
+    assert_eq!(
+        rmp_serde::from_slice::<Key>(&marked).unwrap(),
+        Key::from("hex:00")
+    );
+}
@@ -0,0 +1,465 @@
+//! A compact binary snapshot of a `Tree` that reloads without re-inserting keys.
+//!
+//! A snapshot is a header, the nodes of the tree in preorder and a checksum.
+//! Integers are little-endian and `varint` is an unsigned LEB128.
+//!
+//! ```text
+//! header   := magic:"ARTO" version:u16 flags:u16 count:u64
+//! node     := tag:u8 body
+//!   0x00   None, only for the root of an empty tree
+//!   0x01   Leaf      value
+//!   0x02   Branch    len:varint sequence:[u8; len] node
+//!   0x03   Node4     children
+//!   0x04   Node16    children
+//!   0x05   Node48    children
+//!   0x06   Node256   children
+//!   0x80   set on an inner node tag when the node also holds a value, which
+//!          then comes before its children
+//! children := len:u16 (byte:u8 node){len}, in byte order
+//! value    := len:varint bytes:[u8; len], as written by the `ValueCodec`
+//! trailer  := crc32:u32 of every byte before it
+//! ```
+//!
+//! `count` is the number of values in the tree. Every node keeps its type on a
+//! reload, so the tree comes back with the same shape it was written with.
+
+use crate::{ArtKey, FromArtKey};
+use crate::{BoxNode, Branch, Leaf, Node, Tree};
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"ARTO";
+const VERSION: u16 = 1;
+
+const TAG_NONE: u8 = 0x00;
+const TAG_LEAF: u8 = 0x01;
+const TAG_BRANCH: u8 = 0x02;
+const TAG_NODE4: u8 = 0x03;
+const TAG_NODE16: u8 = 0x04;
+const TAG_NODE48: u8 = 0x05;
+const TAG_NODE256: u8 = 0x06;
+const TAG_WITH_LEAF: u8 = 0x80;
+
+/// Converts the values of a tree to and from the bytes stored in a snapshot.
+pub trait ValueCodec<V> {
+    /// Appends the bytes of `val` to `out`.
+    fn encode(&self, val: &V, out: &mut Vec<u8>);
+
+    /// Decodes a value from exactly the bytes `encode` wrote for it, or returns
+    /// `None` if they are not a valid value.
+    fn decode(&self, bytes: &[u8]) -> Option<V>;
+}
+
+/// Stores values with their `ArtKey` encoding, which covers integers, floats,
+/// strings, byte strings and tuples of them.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ArtKeyCodec;
+
+impl<V: ArtKey + FromArtKey> ValueCodec<V> for ArtKeyCodec {
+    fn encode(&self, val: &V, out: &mut Vec<u8>) {
+        val.encode(out)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Option<V> {
+        V::decode(bytes)
+    }
+}
+
+impl<V> Tree<V> {
+    /// Writes the tree to `writer` in the snapshot format.
+    ///
+    /// Nodes are written a few bytes at a time, so a file or socket should be
+    /// wrapped in a `BufWriter`.
+    ///
+    /// ```
+    /// use artoo::{ArtKeyCodec, Tree};
+    /// let mut tree = Tree::new();
+    /// tree.insert(b"answer", 42u32);
+    /// let mut snapshot = vec![];
+    /// tree.write_snapshot(&mut snapshot, &ArtKeyCodec).unwrap();
+    /// let loaded: Tree<u32> = Tree::read_snapshot(&snapshot[..], &ArtKeyCodec).unwrap();
+    /// assert_eq!(loaded.get(b"answer"), Some(&42));
+    /// ```
+    pub fn write_snapshot<W: Write, C: ValueCodec<V>>(
+        &self,
+        writer: &mut W,
+        codec: &C,
+    ) -> io::Result<()> {
+        let mut w = SnapshotWriter {
+            writer,
+            crc: CRC_INIT,
+            scratch: Vec::new(),
+        };
+        w.write(MAGIC)?;
+        w.write(&VERSION.to_le_bytes())?;
+        w.write(&0u16.to_le_bytes())?;
+        w.write(&(self.count as u64).to_le_bytes())?;
+        w.node(&self.root, codec)?;
+        let crc = !w.crc;
+        w.writer.write_all(&crc.to_le_bytes())
+    }
+
+    /// Reads a tree written by `write_snapshot` with the same value codec.
+    ///
+    /// The whole snapshot is checked before the tree is returned. A bad header,
+    /// checksum, node or value is an `io::ErrorKind::InvalidData` error.
+    pub fn read_snapshot<R: Read, C: ValueCodec<V>>(
+        mut reader: R,
+        codec: &C,
+    ) -> io::Result<Tree<V>> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() < 20 {
+            return Err(invalid("snapshot is truncated"));
+        }
+        let (body, trailer) = bytes.split_at(bytes.len() - 4);
+        if !crc32(CRC_INIT, body) != u32::from_le_bytes(trailer.try_into().unwrap()) {
+            return Err(invalid("snapshot checksum does not match"));
+        }
+        let mut r = SnapshotReader {
+            bytes: body,
+            values: 0,
+        };
+        if r.take(4)? != MAGIC {
+            return Err(invalid("not a snapshot"));
+        }
+        let version = u16::from_le_bytes(r.array()?);
+        if version != VERSION {
+            return Err(invalid("unsupported snapshot version"));
+        }
+        let _flags = u16::from_le_bytes(r.array()?);
+        let count = u64::from_le_bytes(r.array()?) as usize;
+        let root = match r.byte()? {
+            TAG_NONE => Node::None,
+            tag => r.node(tag, codec)?,
+        };
+        if !r.bytes.is_empty() {
+            return Err(invalid("snapshot has trailing bytes"));
+        }
+        if r.values != count {
+            return Err(invalid("snapshot value count does not match"));
+        }
+        Ok(Tree { root, count })
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+struct SnapshotWriter<'w, W> {
+    writer: &'w mut W,
+    crc: u32,
+    scratch: Vec<u8>,
+}
+
+impl<W: Write> SnapshotWriter<'_, W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.crc = crc32(self.crc, bytes);
+        self.writer.write_all(bytes)
+    }
+
+    fn varint(&mut self, mut n: u64) -> io::Result<()> {
+        let mut buf = [0u8; 10];
+        let mut len = 0;
+        loop {
+            let byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                buf[len] = byte;
+                len += 1;
+                break;
+            }
+            buf[len] = byte | 0x80;
+            len += 1;
+        }
+        self.write(&buf[..len])
+    }
+
+    fn value<V, C: ValueCodec<V>>(&mut self, val: &V, codec: &C) -> io::Result<()> {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        codec.encode(val, &mut scratch);
+        self.varint(scratch.len() as u64)?;
+        self.write(&scratch)?;
+        self.scratch = scratch;
+        Ok(())
+    }
+
+    fn node<V, C: ValueCodec<V>>(&mut self, node: &Node<V>, codec: &C) -> io::Result<()> {
+        match node {
+            Node::None | Node::BoxNode(BoxNode::None) => self.write(&[TAG_NONE]),
+            Node::Leaf(leaf) => {
+                self.write(&[TAG_LEAF])?;
+                self.value(&leaf.val, codec)
+            }
+            Node::Branch(branch) => {
+                self.write(&[TAG_BRANCH])?;
+                self.varint(branch.sequence.len() as u64)?;
+                self.write(branch.sequence())?;
+                self.node(&branch.node, codec)
+            }
+            Node::BoxNode(bn) => self.inner(bn, None, codec),
+            Node::BoxNodeLeaf(bn, leaf) => self.inner(bn, Some(leaf), codec),
+        }
+    }
+
+    fn inner<V, C: ValueCodec<V>>(
+        &mut self,
+        bn: &BoxNode<V>,
+        leaf: Option<&Leaf<V>>,
+        codec: &C,
+    ) -> io::Result<()> {
+        let tag = match bn {
+            BoxNode::Node4(_) => TAG_NODE4,
+            BoxNode::Node16(_) => TAG_NODE16,
+            BoxNode::Node48(_) => TAG_NODE48,
+            BoxNode::Node256(_) => TAG_NODE256,
+            BoxNode::None => unreachable!(),
+        };
+        match leaf {
+            Some(leaf) => {
+                self.write(&[tag | TAG_WITH_LEAF])?;
+                self.value(&leaf.val, codec)?;
+            }
+            None => self.write(&[tag])?,
+        }
+        self.write(&(bn.count() as u16).to_le_bytes())?;
+        for (byte, child) in bn.iter() {
+            self.write(&[byte])?;
+            self.node(child, codec)?;
+        }
+        Ok(())
+    }
+}
+
+struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+    values: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(invalid("snapshot is truncated"));
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> io::Result<usize> {
+        let mut n: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return usize::try_from(n).map_err(|_| invalid("snapshot length is too large"));
+            }
+        }
+        Err(invalid("snapshot varint is too long"))
+    }
+
+    fn value<V, C: ValueCodec<V>>(&mut self, codec: &C) -> io::Result<Leaf<V>> {
+        let len = self.varint()?;
+        let bytes = self.take(len)?;
+        let val = codec
+            .decode(bytes)
+            .ok_or_else(|| invalid("snapshot value could not be decoded"))?;
+        self.values += 1;
+        Ok(Leaf::new(val))
+    }
+
+    fn node<V, C: ValueCodec<V>>(&mut self, tag: u8, codec: &C) -> io::Result<Node<V>> {
+        match tag {
+            TAG_LEAF => Ok(Node::Leaf(self.value(codec)?)),
+            TAG_BRANCH => {
+                let len = self.varint()?;
+                let sequence = self.take(len)?;
+                let node = match self.byte()? {
+                    TAG_NONE | TAG_BRANCH => Err(invalid("snapshot Branch has no node")),
+                    tag => self.node(tag, codec),
+                }?;
+                if sequence.is_empty() {
+                    return Err(invalid("snapshot Branch has an empty sequence"));
+                }
+                Ok(Node::Branch(Box::new(Branch::new(sequence, node))))
+            }
+            tag => {
+                let leaf = match tag & TAG_WITH_LEAF {
+                    0 => None,
+                    _ => Some(self.value(codec)?),
+                };
+                let bn = self.inner(tag & !TAG_WITH_LEAF, codec)?;
+                Ok(match leaf {
+                    Some(leaf) => Node::BoxNodeLeaf(bn, leaf),
+                    None => Node::BoxNode(bn),
+                })
+            }
+        }
+    }
+
+    fn inner<V, C: ValueCodec<V>>(&mut self, tag: u8, codec: &C) -> io::Result<BoxNode<V>> {
+        let capacity = match tag {
+            TAG_NODE4 => 4,
+            TAG_NODE16 => 16,
+            TAG_NODE48 => 48,
+            TAG_NODE256 => 256,
+            _ => return Err(invalid("snapshot has an unknown node tag")),
+        };
+        let count = u16::from_le_bytes(self.array()?) as usize;
+        if count == 0 || count > capacity {
+            return Err(invalid("snapshot node has a bad child count"));
+        }
+        let mut children = Vec::with_capacity(count);
+        for _ in 0..count {
+            let byte = self.byte()?;
+            if children.last().is_some_and(|(last, _)| *last >= byte) {
+                return Err(invalid("snapshot node children are out of order"));
+            }
+            let child = match self.byte()? {
+                TAG_NONE => return Err(invalid("snapshot node has an empty child")),
+                tag => self.node(tag, codec)?,
+            };
+            children.push((byte, child));
+        }
+        Ok(BoxNode::with_children(capacity, children))
+    }
+}
+
+const CRC_INIT: u32 = !0;
+
+/// CRC-32 (IEEE) of `bytes`, continuing from `crc`. Start from `CRC_INIT` and
+/// invert the result when done.
+fn crc32(mut crc: u32, bytes: &[u8]) -> u32 {
+    for byte in bytes {
+        crc = CRC_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+use crate::describe::{Describe, Describer};
+
+#[cfg(test)]
+fn describe<V>(tree: &Tree<V>) -> String {
+    let mut desc = Describe::new();
+    tree.describe(&mut desc);
+    desc.string
+}
+
+#[test]
+fn test_snapshot_crc32() {
+    assert_eq!(!crc32(CRC_INIT, b"123456789"), 0xcbf4_3926);
+    assert_eq!(!crc32(CRC_INIT, b""), 0);
+}
+
+#[test]
+fn test_snapshot_round_trips_every_node_type() {
+    let mut tree = Tree::<u64>::new();
+    for i in 0..100_000u64 {
+        tree.insert(&(i * 7).to_be_bytes(), i);
+    }
+    // prefix keys put values on inner nodes and long keys compress into Branches.
+    for word in ["", "a", "ab", "abcdefghijklmnopqrstuvwxyz0123456789"] {
+        tree.insert(word.as_bytes(), word.len() as u64);
+    }
+    let mut snapshot = vec![];
+    tree.write_snapshot(&mut snapshot, &ArtKeyCodec).unwrap();
+    assert_eq!(&snapshot[..4], b"ARTO");
+    let loaded = Tree::<u64>::read_snapshot(&snapshot[..], &ArtKeyCodec).unwrap();
+    assert_eq!(loaded.len(), tree.len());
+    assert_eq!(describe(&loaded), describe(&tree));
+    assert!(loaded.iter().eq(tree.iter()));
+
+    let empty = Tree::<u64>::new();
+    let mut snapshot = vec![];
+    empty.write_snapshot(&mut snapshot, &ArtKeyCodec).unwrap();
+    let loaded = Tree::<u64>::read_snapshot(&snapshot[..], &ArtKeyCodec).unwrap();
+    assert!(loaded.is_empty());
+    assert!(loaded.root.is_none());
+}
+
+#[test]
+fn test_snapshot_pluggable_value_codec() {
+    struct Lengths;
+
+    impl ValueCodec<String> for Lengths {
+        fn encode(&self, val: &String, out: &mut Vec<u8>) {
+            out.push(val.len() as u8);
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Option<String> {
+            Some("x".repeat(*bytes.first()? as usize))
+        }
+    }
+
+    let mut tree = Tree::<String>::new();
+    tree.insert(b"one", String::from("a"));
+    tree.insert(b"three", String::from("abc"));
+    let mut snapshot = vec![];
+    tree.write_snapshot(&mut snapshot, &Lengths).unwrap();
+    let loaded = Tree::<String>::read_snapshot(&snapshot[..], &Lengths).unwrap();
+    assert_eq!(loaded.get(b"three").map(|s| s.as_str()), Some("xxx"));
+    // the default codec reads the same bytes as a different value.
+    let strings = Tree::<String>::read_snapshot(&snapshot[..], &ArtKeyCodec).unwrap();
+    assert_eq!(strings.get(b"one").map(|s| s.as_bytes()), Some(&[1u8][..]));
+}
+
+#[test]
+fn test_snapshot_rejects_corruption() {
+    let mut tree = Tree::<u32>::new();
+    for i in 0..1_000u32 {
+        tree.insert(&i.to_be_bytes(), i);
+    }
+    let mut snapshot = vec![];
+    tree.write_snapshot(&mut snapshot, &ArtKeyCodec).unwrap();
+    let read = |bytes: &[u8]| Tree::<u32>::read_snapshot(bytes, &ArtKeyCodec).map(|_| ());
+
+    assert!(read(&snapshot).is_ok());
+    for i in [0, 5, 20, snapshot.len() / 2, snapshot.len() - 1] {
+        let mut corrupt = snapshot.clone();
+        corrupt[i] ^= 0x10;
+        let err = read(&corrupt).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+    assert!(read(&snapshot[..snapshot.len() - 1]).is_err());
+    assert!(read(&snapshot[..10]).is_err());
+
+    // a valid checksum over a newer version is still rejected.
+    let mut newer = snapshot[..snapshot.len() - 4].to_vec();
+    newer[4] = 2;
+    let crc = !crc32(CRC_INIT, &newer);
+    newer.extend_from_slice(&crc.to_le_bytes());
+    assert_eq!(
+        read(&newer).unwrap_err().to_string(),
+        "unsupported snapshot version"
+    );
+}
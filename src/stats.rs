@@ -0,0 +1,173 @@
+use crate::{BoxNode, Branch, Key, Node, Node16, Node256, Node4, Node48, Tree};
+use std::mem::size_of;
+
+/// The shape of a `Tree` and an estimate of the memory it holds, from
+/// `Tree::stats`.
+///
+/// Depths count the inner nodes (boxed nodes and Branches) passed on the way down
+/// from the root, so a key stored in the root is at depth 0 and the histograms are
+/// indexed by depth.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TreeStats {
+    pub node4: usize,
+    pub node16: usize,
+    pub node48: usize,
+    pub node256: usize,
+    pub branches: usize,
+    pub leaves: usize,
+    /// Leaves stored next to a boxed node, for keys that are a prefix of others.
+    pub box_node_leaves: usize,
+    /// The children of all the boxed nodes.
+    pub children: usize,
+    pub leaf_depths: Vec<usize>,
+    pub inner_depths: Vec<usize>,
+    /// The bytes of every node allocation and every Branch sequence too long to be
+    /// kept inline. Memory that the values own themselves is not included.
+    pub heap_bytes: usize,
+}
+
+impl TreeStats {
+    /// The number of Node4, Node16, Node48 and Node256 nodes.
+    pub fn box_nodes(&self) -> usize {
+        self.node4 + self.node16 + self.node48 + self.node256
+    }
+
+    /// The average number of children of a boxed node.
+    pub fn average_fanout(&self) -> f64 {
+        match self.box_nodes() {
+            0 => 0.0,
+            n => self.children as f64 / n as f64,
+        }
+    }
+}
+
+fn count_at(histogram: &mut Vec<usize>, depth: usize) {
+    if histogram.len() <= depth {
+        histogram.resize(depth + 1, 0);
+    }
+    histogram[depth] += 1;
+}
+
+impl<V> Tree<V> {
+    /// Walks the whole tree to count its nodes by type and depth and to estimate
+    /// the heap memory they use.
+    ///
+    /// ```
+    /// let tree: artoo::Tree<u32> = (0..1_000u32).map(|i| (i.to_be_bytes(), i)).collect();
+    /// let stats = tree.stats();
+    /// assert_eq!(stats.leaves, 1_000);
+    /// assert_eq!(stats.leaf_depths.iter().sum::<usize>(), 1_000);
+    /// assert!(stats.heap_bytes > 0 && stats.average_fanout() > 1.0);
+    /// ```
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        let mut stack = vec![(&self.root, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            match node {
+                Node::None => (),
+                Node::Leaf(_) => {
+                    stats.leaves += 1;
+                    count_at(&mut stats.leaf_depths, depth);
+                }
+                Node::Branch(branch) => {
+                    stats.branches += 1;
+                    count_at(&mut stats.inner_depths, depth);
+                    stats.heap_bytes += size_of::<Branch<V>>();
+                    if let Key::Large(sequence) = &branch.sequence {
+                        stats.heap_bytes += sequence.len();
+                    }
+                    stack.push((&branch.node, depth + 1));
+                }
+                Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => {
+                    if let Node::BoxNodeLeaf(..) = node {
+                        stats.leaves += 1;
+                        stats.box_node_leaves += 1;
+                        count_at(&mut stats.leaf_depths, depth);
+                    }
+                    count_at(&mut stats.inner_depths, depth);
+                    stats.heap_bytes += match bn {
+                        BoxNode::None => 0,
+                        BoxNode::Node4(_) => {
+                            stats.node4 += 1;
+                            size_of::<Node4<V>>()
+                        }
+                        BoxNode::Node16(_) => {
+                            stats.node16 += 1;
+                            size_of::<Node16<V>>()
+                        }
+                        BoxNode::Node48(_) => {
+                            stats.node48 += 1;
+                            size_of::<Node48<V>>()
+                        }
+                        BoxNode::Node256(_) => {
+                            stats.node256 += 1;
+                            size_of::<Node256<V>>()
+                        }
+                    };
+                    for (_, child) in bn.iter() {
+                        stats.children += 1;
+                        stack.push((child, depth + 1));
+                    }
+                }
+            }
+        }
+        stats
+    }
+}
+
+#[test]
+fn test_stats_counts_nodes() {
+    assert_eq!(Tree::<()>::new().stats(), TreeStats::default());
+
+    let mut tree = Tree::new();
+    tree.insert(b"a", ());
+    tree.insert(b"b", ());
+    tree.insert(b"abc", ());
+    tree.insert(b"abcdefghijklmnopqrstuvwxyz0123456789", ());
+    // `a` and `abc` sit next to the Node4 of their longer keys and the longest key
+    // leaves `abcd` as a sequence too long to be kept inline.
+    let stats = tree.stats();
+    assert_eq!((stats.node4, stats.node16), (3, 0));
+    assert_eq!((stats.node48, stats.node256), (0, 0));
+    assert_eq!(
+        (stats.branches, stats.leaves, stats.box_node_leaves),
+        (2, 4, 2)
+    );
+    assert_eq!(stats.children, 4);
+    assert_eq!(stats.leaf_depths, [0, 2, 0, 1, 0, 1]);
+    assert_eq!(stats.inner_depths, [1, 1, 1, 1, 1]);
+    assert_eq!(
+        stats.heap_bytes,
+        3 * size_of::<Node4<()>>() + 2 * size_of::<Branch<()>>() + 32
+    );
+}
+
+#[test]
+fn test_stats_node_growth() {
+    let mut tree = Tree::new();
+    for i in 0..=255u8 {
+        tree.insert(&[i], i);
+        let stats = tree.stats();
+        let (expected, fanout) = match i {
+            0 => ((0, 0, 0, 0), 0.0),
+            1..=3 => ((1, 0, 0, 0), i as f64 + 1.0),
+            4..=15 => ((0, 1, 0, 0), i as f64 + 1.0),
+            16..=47 => ((0, 0, 1, 0), i as f64 + 1.0),
+            _ => ((0, 0, 0, 1), i as f64 + 1.0),
+        };
+        assert_eq!(
+            (stats.node4, stats.node16, stats.node48, stats.node256),
+            expected
+        );
+        assert_eq!(stats.average_fanout(), fanout);
+        assert_eq!(stats.leaves, i as usize + 1);
+    }
+    let mut stats = tree.stats();
+    assert_eq!(stats.leaf_depths, [0, 256]);
+    assert_eq!(stats.heap_bytes, size_of::<Node256<u8>>());
+    for i in 0..=255u8 {
+        tree.remove(&[i]);
+    }
+    stats = tree.stats();
+    assert_eq!(stats, TreeStats::default());
+}
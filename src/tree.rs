@@ -1,9 +1,15 @@
 use crate::describe::{Describe, Describer};
-use crate::{BoxNode, Leaf, Node, Node4, Seek};
+#[cfg(test)]
+use crate::BoxNode;
+use crate::Key;
+use crate::Node;
+use crate::{Entry, OccupiedEntry, VacantEntry};
+use crate::{IntoIter, Iter, IterMut, Keys, PrefixIter, Range, Values, ValuesMut};
+use std::ops::{Bound, RangeBounds};
 
 pub struct Tree<V> {
-    root: Node<V>,
-    count: usize,
+    pub(crate) root: Node<V>,
+    pub(crate) count: usize,
 }
 
 impl<V> Describer for Tree<V> {
@@ -24,99 +30,326 @@ impl<V> Tree<V> {
     }
 
     pub fn get(&self, key: &[u8]) -> Option<&V> {
-        let mut node: *const Node<V> = &self.root as *const Node<V>;
-        let mut seek = Seek::new(key);
-        for (i, byte) in key.iter().enumerate() {
-            seek.idx = i;
-            seek.byte = *byte;
-            match unsafe { &*node }.find_child(seek) {
-                Some(child) => {
-                    node = child;
-                    continue;
-                }
-                None => return None,
+        self.find_node(key)?.leaf().map(|leaf| &leaf.val)
+    }
+
+    /// The entry with the longest key that is a prefix of `key`, with the key given
+    /// as that prefix of `key`.
+    ///
+    /// Every candidate holds its value in a node on the path `get` takes for `key`,
+    /// so one descent finds the deepest of them.
+    ///
+    /// ```
+    /// let mut routes = artoo::Tree::new();
+    /// routes.insert(b"/", "root");
+    /// routes.insert(b"/api/", "api");
+    /// routes.insert(b"/api/users/", "users");
+    /// let (prefix, route) = routes.longest_prefix_match(b"/api/orders/7").unwrap();
+    /// assert_eq!((prefix, *route), (&b"/api/"[..], "api"));
+    /// assert_eq!(routes.longest_prefix_match(b"api"), None);
+    /// ```
+    pub fn longest_prefix_match<'a, 'k>(&'a self, key: &'k [u8]) -> Option<(&'k [u8], &'a V)> {
+        let mut node = &self.root;
+        let mut depth = 0;
+        let mut longest = None;
+        loop {
+            if let Some(leaf) = node.leaf() {
+                longest = Some((&key[..depth], &leaf.val));
+            }
+            if depth == key.len() {
+                return longest;
             }
+            match node.next_node(key, depth) {
+                Some(next) => (node, depth) = next,
+                None => return longest,
+            }
+        }
+    }
+
+    /// Descends to the node whose path from the root is exactly `key`.
+    fn find_node(&self, key: &[u8]) -> Option<&Node<V>> {
+        let mut node = &self.root;
+        let mut depth = 0;
+        while depth < key.len() {
+            (node, depth) = node.next_node(key, depth)?;
         }
-        match unsafe { &*node } {
-            Node::Leaf(leaf) => Some(&leaf.val),
-            Node::BoxNodeLeaf(_, leaf) => Some(&leaf.val),
-            _ => None,
+        Some(node)
+    }
+
+    /// Descends to the shallowest node below which every key starts with `prefix`.
+    ///
+    /// Returns the node with the depth it sits at, which is short of the end of the
+    /// prefix when the prefix ends partway through a Branch.
+    fn find_prefix(&self, prefix: &[u8]) -> Option<(&Node<V>, usize)> {
+        let mut node = &self.root;
+        let mut depth = 0;
+        while depth < prefix.len() {
+            if let Node::Branch(branch) = node {
+                if branch.sequence.starts_with(&prefix[depth..]) {
+                    return Some((node, depth));
+                }
+            }
+            (node, depth) = node.next_node(prefix, depth)?;
         }
+        Some((node, depth))
     }
 
     pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
-        unsafe { std::mem::transmute(self.get(key)) }
+        let mut node = &mut self.root;
+        let mut depth = 0;
+        while depth < key.len() {
+            (node, depth) = node.next_node_mut(key, depth)?;
+        }
+        node.leaf_mut().map(|leaf| &mut leaf.val)
     }
 
+    /// Inserts a value under `key`, returning the previous value if there was one.
+    ///
+    /// Any byte string is a valid key. A key that is a prefix of other keys keeps
+    /// its value on the inner node where the longer keys branch off, and the empty
+    /// key keeps its value on the root.
     pub fn insert(&mut self, key: &[u8], val: V) -> Option<V> {
-        let mut node: *mut Node<V> = &mut self.root as *mut Node<V>;
-        let mut seek = Seek::new(key);
-        for (i, byte) in (&key[..key.len() - 1]).iter().enumerate() {
-            seek.idx = i;
-            seek.byte = *byte;
-            match unsafe { &mut *node }.find_child_mut(seek) {
-                Some(child) => {
+        let (node, depth) = self.root.descend_mut(key);
+        if depth == key.len() {
+            if let Some(leaf) = node.leaf_mut() {
+                return Some(leaf.insert(val));
+            }
+        }
+        node.insert_below(key, depth, val);
+        self.count += 1;
+        None
+    }
+
+    /// Gets the entry for `key` for in-place reads, updates and inserts.
+    ///
+    /// ```
+    /// let mut tree = artoo::Tree::new();
+    /// for word in ["a", "b", "a", "c", "a"] {
+    ///     *tree.entry(word.as_bytes()).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(tree.get(b"a"), Some(&3));
+    /// assert_eq!(tree.get(b"b"), Some(&1));
+    /// ```
+    pub fn entry(&mut self, key: &[u8]) -> Entry<'_, V> {
+        let (steps, anchor) = self.root.trace(key);
+        if let Some(anchor) = anchor {
+            let (node, depth) = self.root.walk_mut(key, anchor);
+            return Entry::Occupied(OccupiedEntry {
+                key: Key::from(key),
+                node,
+                depth,
+                count: &mut self.count,
+            });
+        }
+        let (node, depth) = self.root.walk_mut(key, steps);
+        Entry::Vacant(VacantEntry {
+            key: Key::from(key),
+            node,
+            depth,
+            count: &mut self.count,
+        })
+    }
+
+    /// Removes a key from the tree, returning the value if the key was present.
+    ///
+    /// Inner nodes shrink to the next smaller node type as their occupancy drops and
+    /// nodes left without any children or value are removed from the tree entirely.
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        let val = self.root.remove(key, 0)?;
+        self.count -= 1;
+        Some(val)
+    }
+
+    /// The entry with the smallest key.
+    ///
+    /// The descent takes the lowest child of every node, which is the first of the
+    /// sorted keys in a Node4 or Node16 and the first used slot of a Node48 or Node256.
+    pub fn first_key_value(&self) -> Option<(Key, &V)> {
+        let mut node = &self.root;
+        let mut key = Key::Empty;
+        loop {
+            match node {
+                Node::None => return None,
+                // a node's own value sorts before everything below it.
+                Node::Leaf(leaf) | Node::BoxNodeLeaf(_, leaf) => return Some((key, &leaf.val)),
+                Node::BoxNode(bn) => {
+                    let (byte, child) = bn.next_child(None)?;
+                    key = key.concat(byte);
                     node = child;
-                    continue;
                 }
-                None => {
-                    let new_node4 = Node4::new();
-                    let new_node = Node::BoxNode(BoxNode::Node4(Box::new(new_node4)));
-                    node = unsafe { node.as_mut().unwrap() }.add_child(seek, new_node);
+                Node::Branch(branch) => {
+                    key = key.concat_slice(branch.sequence());
+                    node = &branch.node;
                 }
             }
         }
-        let node = unsafe { &mut *node };
-        seek.idx = key.len() - 1;
-        seek.byte = seek.key[seek.idx];
-        match node.find_child_mut(seek) {
-            Some(child) => child.insert_in_leaf(val),
-            None => {
-                let new_node = Node::Leaf(Leaf::new(val));
-                let _ = node.add_child(seek, new_node);
-                self.count += 1;
-                None
-            }
-        }
     }
 
-    fn remove(&mut self, key: &[u8]) -> Option<V> {
-        let mut node: *mut Node<V> = &mut self.root as *mut Node<V>;
-        let mut seek = Seek::new(key);
-        for (i, byte) in key.iter().enumerate() {
-            seek.idx = i;
-            seek.byte = *byte;
-            match unsafe { &mut *node }.find_child_mut(seek) {
-                Some(child) => {
+    /// The entry with the largest key, found by taking the highest child of every node.
+    pub fn last_key_value(&self) -> Option<(Key, &V)> {
+        let mut node = &self.root;
+        let mut key = Key::Empty;
+        loop {
+            match node {
+                Node::None => return None,
+                Node::Leaf(leaf) => return Some((key, &leaf.val)),
+                Node::BoxNode(bn) | Node::BoxNodeLeaf(bn, _) => {
+                    let (byte, child) = bn.prev_child(None)?;
+                    key = key.concat(byte);
                     node = child;
-                    continue;
                 }
-                None => {
-                    return None;
+                Node::Branch(branch) => {
+                    key = key.concat_slice(branch.sequence());
+                    node = &branch.node;
                 }
             }
         }
-        match unsafe { &mut *node } {
-            node @ Node::Leaf(_) => {
-                let leaf = match std::mem::replace(node, Node::None) {
-                    Node::Leaf(leaf) => leaf,
-                    _ => unreachable!(),
-                };
-                Some(leaf.val)
-            }
-            node @ Node::BoxNodeLeaf(_, _) => {
-                let (box_node, leaf) = match std::mem::replace(node, Node::None) {
-                    Node::BoxNodeLeaf(box_node, leaf) => (box_node, leaf),
-                    _ => unreachable!(),
-                };
-                let none_node = std::mem::replace(node, Node::BoxNode(box_node));
-                debug_assert!(none_node.is_none());
-                Some(leaf.val)
-            }
-            _ => None,
+    }
+
+    /// Removes and returns the entry with the smallest key.
+    pub fn pop_first(&mut self) -> Option<(Key, V)> {
+        let (key, _) = self.first_key_value()?;
+        let val = self.remove(&key)?;
+        Some((key, val))
+    }
+
+    /// Removes and returns the entry with the largest key.
+    pub fn pop_last(&mut self) -> Option<(Key, V)> {
+        let (key, _) = self.last_key_value()?;
+        let val = self.remove(&key)?;
+        Some((key, val))
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Iterates the entries of the tree in lexicographic byte order of their keys.
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter::new(&self.root, self.count)
+    }
+
+    /// Iterates the entries of the tree in key order with mutable access to the values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, V> {
+        IterMut::new(&mut self.root, self.count)
+    }
+
+    /// Iterates the entries whose keys fall within `range`, in key order from either end.
+    ///
+    /// Both ends of the iterator seek straight to their bound by descending the
+    /// tree, so the cost is proportional to the depth plus the entries yielded.
+    ///
+    /// ```
+    /// use std::ops::Bound;
+    /// let mut tree = artoo::Tree::new();
+    /// for i in 0..100u64 {
+    ///     tree.insert(&i.to_be_bytes(), i);
+    /// }
+    /// let lo = 10u64.to_be_bytes();
+    /// let hi = 20u64.to_be_bytes();
+    /// let vals: Vec<u64> = tree.range(&lo[..]..&hi[..]).map(|(_, v)| *v).collect();
+    /// assert_eq!(vals, (10..20).collect::<Vec<_>>());
+    /// let last = tree.range::<[u8], _>((Bound::Excluded(&lo[..]), Bound::Unbounded)).next_back();
+    /// assert_eq!(last.map(|(_, v)| *v), Some(99));
+    /// ```
+    pub fn range<K, R>(&self, range: R) -> Range<'_, V>
+    where
+        K: AsRef<[u8]> + ?Sized,
+        R: RangeBounds<K>,
+    {
+        let lower = as_byte_bound(range.start_bound());
+        let upper = as_byte_bound(range.end_bound());
+        Range::new(&self.root, lower, upper)
+    }
+
+    /// Iterates the entries whose keys start with `prefix`, in key order from either end.
+    ///
+    /// ```
+    /// let mut tree = artoo::Tree::new();
+    /// tree.insert(b"car", 1);
+    /// tree.insert(b"cart", 2);
+    /// tree.insert(b"cat", 3);
+    /// let keys: Vec<artoo::Key> = tree.prefix_iter(b"car").map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec![artoo::Key::from("car"), artoo::Key::from("cart")]);
+    /// ```
+    pub fn prefix_iter(&self, prefix: &[u8]) -> PrefixIter<'_, V> {
+        match self.find_prefix(prefix) {
+            Some((node, depth)) => PrefixIter(Range::subtree(Key::from(&prefix[..depth]), node)),
+            None => PrefixIter(Range::empty()),
         }
-        // for performance's sake - we don't need to shrink when a node is empty.
-        // TODO: do we need a policy?
+    }
+
+    /// Counts the entries whose keys start with `prefix`.
+    pub fn prefix_count(&self, prefix: &[u8]) -> usize {
+        self.find_prefix(prefix)
+            .map_or(0, |(node, _)| node.count_values())
+    }
+
+    /// Removes every entry whose key starts with `prefix`, returning how many were removed.
+    ///
+    /// The whole subtree is detached from its parent in one step, which then shrinks
+    /// the same way it does for `remove`.
+    pub fn remove_prefix(&mut self, prefix: &[u8]) -> usize {
+        let removed = self.root.remove_prefix(prefix, 0);
+        self.count -= removed;
+        removed
+    }
+
+    pub fn keys(&self) -> Keys<'_, V> {
+        Keys(self.iter())
+    }
+
+    pub fn values(&self) -> Values<'_, V> {
+        Values(self.iter())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, V> {
+        ValuesMut(self.iter_mut())
+    }
+}
+
+impl<'a, V> IntoIterator for &'a Tree<V> {
+    type Item = (Key, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Iter<'a, V> {
+        self.iter()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a mut Tree<V> {
+    type Item = (Key, &'a mut V);
+    type IntoIter = IterMut<'a, V>;
+
+    fn into_iter(self) -> IterMut<'a, V> {
+        self.iter_mut()
+    }
+}
+
+impl<V> IntoIterator for Tree<V> {
+    type Item = (Key, V);
+    type IntoIter = IntoIter<V>;
+
+    fn into_iter(self) -> IntoIter<V> {
+        IntoIter::new(self.root, self.count)
+    }
+}
+
+fn as_byte_bound<K: AsRef<[u8]> + ?Sized>(bound: Bound<&K>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.as_ref()),
+        Bound::Excluded(key) => Bound::Excluded(key.as_ref()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+impl<V> Default for Tree<V> {
+    fn default() -> Self {
+        Tree::new()
     }
 }
 
@@ -125,17 +358,19 @@ fn test_tree_insert() {
     let mut tree = Tree::<i32>::new();
     let ins1 = tree.insert(b"a", 1);
     assert_eq!(ins1, None);
+    let ins2 = tree.insert(b"b", 2);
+    assert_eq!(ins2, None);
     {
         match &tree.root {
             Node::BoxNode(bn) => match bn {
                 BoxNode::Node4(n4) => {
-                    // 97 is 'a'
-                    assert_eq!(n4.count, 1);
-                    assert_eq!(n4.key, [97, 0, 0, 0]);
-                    assert!(n4.children[0].is_none() == false);
-                    assert!(n4.children[1].is_none() == true);
-                    assert!(n4.children[2].is_none() == true);
-                    assert!(n4.children[3].is_none() == true);
+                    // 97 is 'a' and 98 is 'b'
+                    assert_eq!(n4.count, 2);
+                    assert_eq!(n4.key, [97, 98, 0, 0]);
+                    assert!(!n4.children[0].is_none());
+                    assert!(!n4.children[1].is_none());
+                    assert!(n4.children[2].is_none());
+                    assert!(n4.children[3].is_none());
 
                     let node: &Node<i32> = &n4.children[0];
                     match node {
@@ -154,9 +389,10 @@ fn test_tree_insert() {
     tree.describe(&mut desc);
     desc.assert_eq(
         "
-Tree(size: 1)
+Tree(size: 2)
   Node::BoxNode => BoxNode::Node4 => Node4
     97 => Node::Leaf => Leaf(val: :no_debug:)
+    98 => Node::Leaf => Leaf(val: :no_debug:)
         ",
     )
 }
@@ -221,3 +457,649 @@ fn test_tree_can_grow_bigger_than_256_keys() {
 fn test_tree_handle_100k_keys() {
     let _ = tree_with_n_keys(100_000);
 }
+
+#[test]
+fn test_tree_remove_returns_the_old_value() {
+    let mut tree = Tree::<i32>::new();
+    tree.insert(b"ab", 1);
+    tree.insert(b"ac", 2);
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree.remove(b"ab"), Some(1));
+    assert_eq!(tree.len(), 1);
+    assert_eq!(tree.get(b"ab"), None);
+    assert_eq!(tree.get(b"ac"), Some(&2));
+    assert_eq!(tree.remove(b"ab"), None);
+    assert_eq!(tree.remove(b"zz"), None);
+    assert_eq!(tree.len(), 1);
+}
+
+#[test]
+fn test_tree_remove_keeps_prefix_keys() {
+    let mut tree = Tree::<i32>::new();
+    tree.insert(b"a", 1);
+    tree.insert(b"ab", 2);
+    assert_eq!(tree.remove(b"a"), Some(1));
+    assert_eq!(tree.get(b"ab"), Some(&2));
+    tree.insert(b"a", 1);
+    assert_eq!(tree.remove(b"ab"), Some(2));
+    assert_eq!(tree.get(b"a"), Some(&1));
+    assert_eq!(tree.len(), 1);
+}
+
+#[test]
+fn test_tree_insert_counts_values_on_existing_inner_nodes() {
+    let mut tree = Tree::<i32>::new();
+    tree.insert(b"ab", 1);
+    assert_eq!(tree.insert(b"a", 2), None);
+    assert_eq!(tree.len(), 2);
+}
+
+#[cfg(test)]
+fn root_box_node<V>(tree: &Tree<V>) -> &BoxNode<V> {
+    match &tree.root {
+        Node::BoxNode(bn) => bn,
+        got => {
+            let mut desc = Describe::new();
+            got.describe(&mut desc);
+            panic!("tree.root was not a BoxNode - got: {}", desc.as_str())
+        }
+    }
+}
+
+#[test]
+fn test_tree_remove_shrinks_nodes() {
+    let mut tree = Tree::<usize>::new();
+    for i in 0..256 {
+        tree.insert(&[i as u8], i);
+    }
+    assert!(matches!(root_box_node(&tree), BoxNode::Node256(_)));
+    for i in (37..256).rev() {
+        assert_eq!(tree.remove(&[i as u8]), Some(i));
+    }
+    assert!(matches!(root_box_node(&tree), BoxNode::Node48(_)));
+    for i in (12..37).rev() {
+        assert_eq!(tree.remove(&[i as u8]), Some(i));
+    }
+    assert!(matches!(root_box_node(&tree), BoxNode::Node16(_)));
+    for i in (3..12).rev() {
+        assert_eq!(tree.remove(&[i as u8]), Some(i));
+    }
+    assert!(matches!(root_box_node(&tree), BoxNode::Node4(_)));
+    for i in 0..3 {
+        assert_eq!(tree.get(&[i as u8]), Some(&i));
+    }
+    for i in 0..3 {
+        assert_eq!(tree.remove(&[i as u8]), Some(i));
+    }
+    assert!(tree.root.is_none());
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn test_tree_remove_all_of_100k_keys() {
+    let n = 100_000;
+    let mut tree = tree_with_n_keys(n);
+    for i in (0..n).step_by(2) {
+        assert_eq!(tree.remove(&i.to_be_bytes()[..]), Some(i));
+    }
+    assert_eq!(tree.len(), n / 2);
+    for i in 0..n {
+        let expected = if i % 2 == 0 { None } else { Some(&i) };
+        assert_eq!(tree.get(&i.to_be_bytes()[..]), expected);
+    }
+    for i in (1..n).step_by(2) {
+        assert_eq!(tree.remove(&i.to_be_bytes()[..]), Some(i));
+    }
+    assert!(tree.is_empty());
+    assert!(tree.root.is_none());
+}
+
+#[test]
+fn test_tree_iter_is_in_byte_order() {
+    use rand::seq::SliceRandom;
+    use std::collections::BTreeMap;
+
+    let mut keys: Vec<Vec<u8>> = (0..2_000usize)
+        .map(|i| (i * 7919 % 100_003).to_string().into_bytes())
+        .collect();
+    keys.push(b"1".to_vec());
+    keys.push(b"12".to_vec());
+    keys.push(b"123".to_vec());
+    keys.shuffle(&mut rand::thread_rng());
+
+    let mut tree = Tree::<usize>::new();
+    let mut btree = BTreeMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(tree.insert(key, i), btree.insert(key.clone(), i));
+    }
+    let got: Vec<(Vec<u8>, usize)> = tree.iter().map(|(k, v)| (k.to_vec(), *v)).collect();
+    let expected: Vec<(Vec<u8>, usize)> = btree.into_iter().collect();
+    assert_eq!(got.len(), tree.len());
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn test_tree_iter_crosses_every_node_type() {
+    let n = 70_000;
+    let tree = tree_with_n_keys(n);
+    assert_eq!(tree.iter().len(), n);
+    for (i, (key, val)) in tree.iter().enumerate() {
+        assert_eq!(usize::from(&key), i);
+        assert_eq!(*val, i);
+    }
+    assert!(tree.keys().map(usize::from).eq(0..n));
+    assert!(tree.values().copied().eq(0..n));
+}
+
+#[test]
+fn test_tree_values_mut() {
+    let mut tree = tree_with_n_keys(300);
+    for val in tree.values_mut() {
+        *val *= 2;
+    }
+    for (key, val) in &mut tree {
+        assert_eq!(*val, usize::from(&key) * 2);
+        *val += 1;
+    }
+    assert!(tree.values().copied().eq((0..300).map(|i| i * 2 + 1)));
+}
+
+#[test]
+fn test_tree_into_iter() {
+    let mut tree = Tree::<String>::new();
+    tree.insert(b"b", "b".to_string());
+    tree.insert(b"a", "a".to_string());
+    tree.insert(b"ab", "ab".to_string());
+    let got: Vec<(Key, String)> = tree.into_iter().collect();
+    assert_eq!(
+        got,
+        vec![
+            (Key::from("a"), "a".to_string()),
+            (Key::from("ab"), "ab".to_string()),
+            (Key::from("b"), "b".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_tree_iter_rev() {
+    let tree = tree_with_n_keys(1_000);
+    assert!(tree.values().rev().copied().eq((0..1_000).rev()));
+    let mut iter = tree.iter();
+    assert_eq!(iter.next().map(|(_, v)| *v), Some(0));
+    assert_eq!(iter.next_back().map(|(_, v)| *v), Some(999));
+    assert_eq!(iter.len(), 998);
+    assert_eq!(iter.count(), 998);
+}
+
+#[test]
+fn test_tree_range_matches_btree_map() {
+    use std::collections::BTreeMap;
+
+    let words = [
+        "", "a", "ab", "abc", "abd", "b", "ba", "bab", "c", "ca", "cab", "cb", "d", "zz",
+    ];
+    let mut tree = Tree::<usize>::new();
+    let mut btree = BTreeMap::<Vec<u8>, usize>::new();
+    for (i, word) in words.iter().enumerate().skip(1) {
+        tree.insert(word.as_bytes(), i);
+        btree.insert(word.as_bytes().to_vec(), i);
+    }
+    let bounds = |word: &'static str| {
+        [
+            Bound::Included(word.as_bytes()),
+            Bound::Excluded(word.as_bytes()),
+            Bound::Unbounded,
+        ]
+    };
+    for lo_word in words.iter().chain(["aa", "abcd", "bb", "e"].iter()) {
+        for hi_word in words.iter().chain(["aa", "abcd", "bb", "e"].iter()) {
+            for lo in bounds(lo_word) {
+                for hi in bounds(hi_word) {
+                    let got: Vec<(Vec<u8>, usize)> = tree
+                        .range::<[u8], _>((lo, hi))
+                        .map(|(k, v)| (k.to_vec(), *v))
+                        .collect();
+                    let expected: Vec<(Vec<u8>, usize)> = btree
+                        .iter()
+                        .filter(|(k, _)| (lo, hi).contains(&k[..]))
+                        .map(|(k, v)| (k.clone(), *v))
+                        .collect();
+                    assert_eq!(got, expected, "range {:?}..{:?}", lo, hi);
+                    let mut rev: Vec<(Vec<u8>, usize)> = tree
+                        .range::<[u8], _>((lo, hi))
+                        .rev()
+                        .map(|(k, v)| (k.to_vec(), *v))
+                        .collect();
+                    rev.reverse();
+                    assert_eq!(rev, expected, "rev range {:?}..{:?}", lo, hi);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_tree_range_ends_meet() {
+    let tree = tree_with_n_keys(300);
+    let lo = 100usize.to_be_bytes();
+    let hi = 200usize.to_be_bytes();
+    let mut range = tree.range(&lo[..]..=&hi[..]);
+    let mut got = vec![];
+    loop {
+        match (range.next(), range.next_back()) {
+            (Some((_, a)), Some((_, b))) => got.extend([*a, *b]),
+            (Some((_, a)), None) | (None, Some((_, a))) => got.push(*a),
+            (None, None) => break,
+        }
+    }
+    got.sort();
+    assert_eq!(got, (100..=200).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_tree_range_over_big_endian_timestamps() {
+    let tree = tree_with_n_keys(100_000);
+    let lo = 12_345usize.to_be_bytes();
+    let hi = 67_890usize.to_be_bytes();
+    assert!(tree
+        .range(&lo[..]..&hi[..])
+        .map(|(k, v)| {
+            assert_eq!(usize::from(&k), *v);
+            *v
+        })
+        .eq(12_345..67_890));
+    assert!(tree.range(&hi[..]..).map(|(_, v)| *v).eq(67_890..100_000));
+    assert!(tree
+        .range(..=&lo[..])
+        .rev()
+        .map(|(_, v)| *v)
+        .eq((0..=12_345).rev()));
+}
+
+#[test]
+fn test_tree_prefix_iter() {
+    let mut tree = Tree::<usize>::new();
+    let words = [
+        "ca", "car", "card", "care", "cart", "cat", "co", "d", "carts",
+    ];
+    for (i, word) in words.iter().enumerate() {
+        tree.insert(word.as_bytes(), i);
+    }
+    let keys = |iter: PrefixIter<usize>| -> Vec<String> {
+        iter.map(|(k, _)| String::from_utf8(k.to_vec()).unwrap())
+            .collect()
+    };
+    assert_eq!(
+        keys(tree.prefix_iter(b"car")),
+        vec!["car", "card", "care", "cart", "carts"]
+    );
+    assert_eq!(
+        keys(tree.prefix_iter(b"car"))
+            .into_iter()
+            .rev()
+            .collect::<Vec<_>>(),
+        tree.prefix_iter(b"car")
+            .rev()
+            .map(|(k, _)| String::from_utf8(k.to_vec()).unwrap())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(keys(tree.prefix_iter(b"cart")), vec!["cart", "carts"]);
+    assert_eq!(keys(tree.prefix_iter(b"cb")), Vec::<String>::new());
+    assert_eq!(keys(tree.prefix_iter(b"cards")), Vec::<String>::new());
+    assert_eq!(tree.prefix_iter(b"").count(), words.len());
+    assert_eq!(tree.prefix_count(b"c"), 8);
+    assert_eq!(tree.prefix_count(b"car"), 5);
+    assert_eq!(tree.prefix_count(b"x"), 0);
+    assert_eq!(tree.prefix_count(b""), words.len());
+}
+
+#[test]
+fn test_tree_remove_prefix() {
+    let mut tree = tree_with_n_keys(70_000);
+    // every key from 0x0100 to 0x01ff shares the first seven bytes.
+    let prefix = &256usize.to_be_bytes()[..7];
+    assert_eq!(tree.prefix_count(prefix), 256);
+    assert_eq!(tree.remove_prefix(prefix), 256);
+    assert_eq!(tree.len(), 70_000 - 256);
+    assert_eq!(tree.prefix_count(prefix), 0);
+    assert_eq!(tree.get(&255usize.to_be_bytes()), Some(&255));
+    assert_eq!(tree.get(&256usize.to_be_bytes()), None);
+    assert_eq!(tree.get(&512usize.to_be_bytes()), Some(&512));
+    assert_eq!(tree.remove_prefix(prefix), 0);
+    assert_eq!(tree.iter().count(), tree.len());
+
+    assert_eq!(tree.remove_prefix(b""), 70_000 - 256);
+    assert!(tree.is_empty());
+    assert!(tree.root.is_none());
+}
+
+#[test]
+fn test_tree_compresses_single_child_paths() {
+    let mut tree = Tree::<i32>::new();
+    tree.insert(b"abcdef", 1);
+    let mut desc = Describe::new();
+    tree.describe(&mut desc);
+    desc.assert_eq(
+        "
+Tree(size: 1)
+  Node::Branch => Branch(seq: [97, 98, 99, 100, 101, 102])
+    Node::Leaf => Leaf(val: :no_debug:)
+        ",
+    );
+
+    // a mismatch in the middle splits the sequence around a Node4.
+    tree.insert(b"abcxyz", 2);
+    let mut desc = Describe::new();
+    tree.describe(&mut desc);
+    desc.assert_eq(
+        "
+Tree(size: 2)
+  Node::Branch => Branch(seq: [97, 98, 99])
+    Node::BoxNode => BoxNode::Node4 => Node4
+      100 => Node::Branch => Branch(seq: [101, 102])
+        Node::Leaf => Leaf(val: :no_debug:)
+      120 => Node::Branch => Branch(seq: [121, 122])
+        Node::Leaf => Leaf(val: :no_debug:)
+        ",
+    );
+    assert_eq!(tree.get(b"abcdef"), Some(&1));
+    assert_eq!(tree.get(b"abcxyz"), Some(&2));
+    assert_eq!(tree.get(b"abc"), None);
+    assert_eq!(tree.get(b"abcd"), None);
+    assert_eq!(tree.get(b"abcdefg"), None);
+
+    // removing a key merges the remaining path back into one Branch.
+    assert_eq!(tree.remove(b"abcxyz"), Some(2));
+    let mut desc = Describe::new();
+    tree.describe(&mut desc);
+    desc.assert_eq(
+        "
+Tree(size: 1)
+  Node::Branch => Branch(seq: [97, 98, 99, 100, 101, 102])
+    Node::Leaf => Leaf(val: :no_debug:)
+        ",
+    );
+    assert_eq!(tree.remove(b"abc"), None);
+    assert_eq!(tree.remove(b"abcdef"), Some(1));
+    assert!(tree.root.is_none());
+}
+
+#[test]
+fn test_tree_compressed_keys_match_btree_map() {
+    use std::collections::BTreeMap;
+
+    let mut tree = Tree::<usize>::new();
+    let mut btree = BTreeMap::<Vec<u8>, usize>::new();
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for i in 0..5_000 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        // long shared prefixes with short random tails.
+        let mut key = b"/users/profile/".to_vec();
+        key.extend_from_slice(&state.to_be_bytes()[..(state % 4) as usize + 1]);
+        key.extend_from_slice(&b"/settings"[..(state % 9) as usize]);
+        assert_eq!(tree.insert(&key, i), btree.insert(key, i));
+    }
+    assert_eq!(tree.len(), btree.len());
+    assert!(tree.iter().map(|(k, v)| (k.to_vec(), *v)).eq(btree.clone()));
+    assert!(tree
+        .iter()
+        .rev()
+        .map(|(k, v)| (k.to_vec(), *v))
+        .eq(btree.clone().into_iter().rev()));
+
+    let keys: Vec<Vec<u8>> = btree.keys().cloned().collect();
+    for pair in keys.chunks(2).take(500) {
+        let (lo, hi) = (&pair[0][..], &pair[pair.len() - 1][..10]);
+        let got: Vec<usize> = tree.range(lo..).map(|(_, v)| *v).collect();
+        let expected: Vec<usize> = btree.range(pair[0].clone()..).map(|(_, v)| *v).collect();
+        assert_eq!(got, expected);
+        assert_eq!(
+            tree.range(..=hi).count(),
+            btree.keys().filter(|k| &k[..] <= hi).count()
+        );
+        assert_eq!(
+            tree.prefix_count(&lo[..16]),
+            btree.keys().filter(|k| k.starts_with(&lo[..16])).count()
+        );
+    }
+
+    for key in keys.iter().step_by(2) {
+        assert_eq!(tree.remove(key), btree.remove(key));
+    }
+    assert_eq!(tree.len(), btree.len());
+    assert!(tree.iter().map(|(k, v)| (k.to_vec(), *v)).eq(btree));
+}
+
+#[test]
+fn test_tree_expands_single_key_subtrees_lazily() {
+    let mut tree = Tree::<i32>::new();
+    tree.insert(b"apple", 1);
+    tree.insert(b"banana", 2);
+    tree.insert(b"b", 3);
+    let mut desc = Describe::new();
+    tree.describe(&mut desc);
+    desc.assert_eq(
+        "
+Tree(size: 3)
+  Node::BoxNode => BoxNode::Node4 => Node4
+    97 => Node::Branch => Branch(seq: [112, 112, 108, 101])
+      Node::Leaf => Leaf(val: :no_debug:)
+    98 => Node::BoxNodeLeaf => BoxNode::Node4 => Node4
+      97 => Node::Branch => Branch(seq: [110, 97, 110, 97])
+        Node::Leaf => Leaf(val: :no_debug:)
+        ",
+    );
+    match &tree.root {
+        Node::BoxNode(BoxNode::Node4(n4)) => match &n4.children[0] {
+            // the suffix of a short key is stored inline with its leaf.
+            Node::Branch(branch) => assert!(matches!(branch.sequence, Key::Small(4, _))),
+            got => panic!("expected Node::Branch got: {:?}", got),
+        },
+        got => panic!("tree.root was not a Node4 - got: {:?}", got),
+    }
+
+    // a second key expands the shared part of the suffix into inner nodes.
+    tree.insert(b"apply", 4);
+    let mut desc = Describe::new();
+    tree.describe(&mut desc);
+    desc.assert_eq(
+        "
+Tree(size: 4)
+  Node::BoxNode => BoxNode::Node4 => Node4
+    97 => Node::Branch => Branch(seq: [112, 112, 108])
+      Node::BoxNode => BoxNode::Node4 => Node4
+        101 => Node::Leaf => Leaf(val: :no_debug:)
+        121 => Node::Leaf => Leaf(val: :no_debug:)
+    98 => Node::BoxNodeLeaf => BoxNode::Node4 => Node4
+      97 => Node::Branch => Branch(seq: [110, 97, 110, 97])
+        Node::Leaf => Leaf(val: :no_debug:)
+        ",
+    );
+    assert_eq!(tree.get(b"apple"), Some(&1));
+    assert_eq!(tree.get(b"apply"), Some(&4));
+    assert_eq!(tree.get(b"app"), None);
+}
+
+#[test]
+fn test_tree_empty_key() {
+    let mut tree = Tree::<i32>::new();
+    assert_eq!(tree.get(b""), None);
+    assert_eq!(tree.remove(b""), None);
+    assert_eq!(tree.insert(b"", 1), None);
+    assert_eq!(tree.get(b""), Some(&1));
+    assert_eq!(tree.insert(b"", 2), Some(1));
+    assert_eq!(tree.len(), 1);
+    assert!(matches!(tree.root, Node::Leaf(_)));
+
+    tree.insert(b"abc", 3);
+    // the empty key is stored on the root itself.
+    let mut desc = Describe::new();
+    tree.describe(&mut desc);
+    desc.assert_eq(
+        "
+Tree(size: 2)
+  Node::BoxNodeLeaf => BoxNode::Node4 => Node4
+    97 => Node::Branch => Branch(seq: [98, 99])
+      Node::Leaf => Leaf(val: :no_debug:)
+        ",
+    );
+    assert_eq!(tree.get(b""), Some(&2));
+    assert_eq!(tree.get(b"abc"), Some(&3));
+    let keys: Vec<Key> = tree.keys().collect();
+    assert_eq!(keys, vec![Key::from(""), Key::from("abc")]);
+    assert_eq!(tree.iter().next_back().map(|(_, v)| *v), Some(3));
+    assert_eq!(tree.range(&b""[..]..&b"a"[..]).count(), 1);
+    assert_eq!(
+        tree.range::<[u8], _>((Bound::Excluded(&b""[..]), Bound::Unbounded))
+            .count(),
+        1
+    );
+    assert_eq!(
+        tree.range(..=&b""[..]).map(|(_, v)| *v).collect::<Vec<_>>(),
+        vec![2]
+    );
+    assert_eq!(tree.prefix_count(b""), 2);
+
+    assert_eq!(tree.remove(b""), Some(2));
+    assert_eq!(tree.get(b""), None);
+    assert_eq!(tree.get(b"abc"), Some(&3));
+    assert!(matches!(tree.root, Node::Branch(_)));
+    assert_eq!(tree.remove(b"abc"), Some(3));
+    assert!(tree.root.is_none());
+
+    // the empty key also works when it arrives after a compressed root.
+    tree.insert(b"abc", 3);
+    tree.insert(b"", 4);
+    assert_eq!(tree.get(b""), Some(&4));
+    assert_eq!(tree.get(b"abc"), Some(&3));
+    assert_eq!(tree.iter().map(|(_, v)| *v).collect::<Vec<_>>(), vec![4, 3]);
+}
+
+#[test]
+fn test_tree_keys_that_prefix_each_other() {
+    let words = ["", "a", "ab", "abc", "abcdefgh", "abcdefghij", "abcdx"];
+    let orders: [Vec<&str>; 2] = [words.to_vec(), words.iter().rev().copied().collect()];
+    for order in orders {
+        let mut tree = Tree::<usize>::new();
+        for word in &order {
+            assert_eq!(tree.insert(word.as_bytes(), word.len()), None);
+        }
+        assert_eq!(tree.len(), words.len());
+        for word in &words {
+            assert_eq!(tree.get(word.as_bytes()), Some(&word.len()), "{:?}", word);
+        }
+        for missing in ["abcd", "abcdefg", "abcdefghi", "abcdefghijk", "b"] {
+            assert_eq!(tree.get(missing.as_bytes()), None, "{:?}", missing);
+        }
+        let keys: Vec<Key> = tree.keys().collect();
+        let expected: Vec<Key> = words.iter().map(|w| Key::from(*w)).collect();
+        assert_eq!(keys, expected);
+        assert_eq!(tree.prefix_count(b"abcdefgh"), 2);
+        assert_eq!(tree.prefix_count(b"abcd"), 3);
+
+        // removing in the same order leaves the longer or shorter keys intact.
+        for (i, word) in order.iter().enumerate() {
+            assert_eq!(tree.remove(word.as_bytes()), Some(word.len()));
+            assert_eq!(tree.get(word.as_bytes()), None);
+            for rest in &order[i + 1..] {
+                assert_eq!(tree.get(rest.as_bytes()), Some(&rest.len()), "{:?}", rest);
+            }
+            assert_eq!(tree.iter().count(), order.len() - i - 1);
+        }
+        assert!(tree.root.is_none());
+    }
+}
+
+#[test]
+fn test_tree_first_last_and_pop() {
+    let mut tree = Tree::<u64>::new();
+    assert_eq!(tree.first_key_value(), None);
+    assert_eq!(tree.pop_last(), None);
+    for word in ["b", "", "ab", "a", "abc", "ba"] {
+        tree.insert(word.as_bytes(), word.len() as u64);
+    }
+    assert_eq!(tree.first_key_value(), Some((Key::from(""), &0)));
+    assert_eq!(tree.last_key_value(), Some((Key::from("ba"), &2)));
+    assert_eq!(tree.pop_first(), Some((Key::from(""), 0)));
+    assert_eq!(tree.pop_last(), Some((Key::from("ba"), 2)));
+    assert_eq!(tree.pop_last(), Some((Key::from("b"), 1)));
+    assert_eq!(tree.first_key_value(), Some((Key::from("a"), &1)));
+    assert_eq!(tree.last_key_value(), Some((Key::from("abc"), &3)));
+
+    // used as a priority queue of big-endian deadlines, through every node type.
+    let mut queue = Tree::new();
+    let mut heap = std::collections::BinaryHeap::new();
+    let mut state = 0x243f_6a88u64;
+    for round in 0..20_000u64 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        if round % 3 == 2 {
+            let popped = queue.pop_first().map(|(k, v): (Key, u64)| {
+                assert_eq!(k.as_slice(), v.to_be_bytes());
+                v
+            });
+            assert_eq!(popped, heap.pop().map(|std::cmp::Reverse(v)| v));
+        } else {
+            let deadline = state % 100_000;
+            if queue.insert(&deadline.to_be_bytes(), deadline).is_none() {
+                heap.push(std::cmp::Reverse(deadline));
+            }
+        }
+        if round % 500 == 0 {
+            assert_eq!(
+                queue.last_key_value().map(|(_, v)| *v),
+                heap.iter().map(|r| r.0).max()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_tree_longest_prefix_match() {
+    let mut tree = Tree::<usize>::new();
+    let prefixes = ["", "a", "abc", "abcdefghijklmnopqrstuvwxyz0123", "b", "bcd"];
+    for prefix in prefixes {
+        tree.insert(prefix.as_bytes(), prefix.len());
+    }
+    for i in 0..300u32 {
+        tree.insert(format!("abc{}", i).as_bytes(), 1_000 + i as usize);
+    }
+    for probe in [
+        "",
+        "a",
+        "ab",
+        "abc",
+        "abcd",
+        "abc12",
+        "abc123",
+        "abc1234",
+        "abcdefghijklmnopqrstuvwxyz0",
+        "abcdefghijklmnopqrstuvwxyz01234",
+        "b",
+        "bc",
+        "bcde",
+        "c",
+    ] {
+        // the same answer as trying every prefix from the longest down.
+        let expected = (0..=probe.len()).rev().find_map(|n| {
+            tree.get(&probe.as_bytes()[..n])
+                .map(|v| (&probe.as_bytes()[..n], v))
+        });
+        assert_eq!(
+            tree.longest_prefix_match(probe.as_bytes()),
+            expected,
+            "{:?}",
+            probe
+        );
+    }
+    tree.remove(b"");
+    assert_eq!(tree.longest_prefix_match(b"c"), None);
+    assert_eq!(
+        tree.longest_prefix_match(b"abc12x"),
+        Some((&b"abc12"[..], &1_012))
+    );
+}
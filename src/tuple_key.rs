@@ -0,0 +1,252 @@
+use crate::{ArtKey, FromArtKey, Key};
+
+/// A type that can be one component of a tuple key.
+///
+/// Each component must be self-delimiting so the next component can follow it. A
+/// fixed-width component is its `ArtKey` encoding. A variable-length component
+/// escapes every `0x00` byte as `0x00 0xFF` and ends with `0x00 0x01`, which
+/// sorts a component below any longer one it is a prefix of. Either way the
+/// encoding of a tuple compares like the tuple and the encoding of its leading
+/// components is a byte prefix of the whole.
+pub trait KeyComponent {
+    /// Appends the component to `out`.
+    fn encode_component(&self, out: &mut Vec<u8>);
+}
+
+/// A tuple key component that can be decoded back from a tuple key.
+pub trait FromKeyComponent: Sized {
+    /// Decodes the component at the start of `bytes`, returning it with the number
+    /// of bytes it used, or `None` if `bytes` does not start with one.
+    fn decode_component(bytes: &[u8]) -> Option<(Self, usize)>;
+}
+
+const ESCAPE: u8 = 0x00;
+const ESCAPED_ZERO: u8 = 0xFF;
+const TERMINATOR: u8 = 0x01;
+
+fn encode_escaped(bytes: &[u8], out: &mut Vec<u8>) {
+    for chunk in bytes.split_inclusive(|b| *b == ESCAPE) {
+        out.extend_from_slice(chunk);
+        if chunk.ends_with(&[ESCAPE]) {
+            out.push(ESCAPED_ZERO);
+        }
+    }
+    out.extend_from_slice(&[ESCAPE, TERMINATOR]);
+}
+
+fn decode_escaped(bytes: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    loop {
+        let zero = i + bytes[i..].iter().position(|b| *b == ESCAPE)?;
+        out.extend_from_slice(&bytes[i..zero]);
+        match *bytes.get(zero + 1)? {
+            ESCAPED_ZERO => out.push(ESCAPE),
+            TERMINATOR => return Some((out, zero + 2)),
+            _ => return None,
+        }
+        i = zero + 2;
+    }
+}
+
+impl<T: KeyComponent + ?Sized> KeyComponent for &T {
+    fn encode_component(&self, out: &mut Vec<u8>) {
+        (**self).encode_component(out)
+    }
+}
+
+macro_rules! key_component_for_fixed_width {
+    ($($ty:ty),*) => {
+        $(
+            impl KeyComponent for $ty {
+                fn encode_component(&self, out: &mut Vec<u8>) {
+                    self.encode(out)
+                }
+            }
+
+            impl FromKeyComponent for $ty {
+                fn decode_component(bytes: &[u8]) -> Option<(Self, usize)> {
+                    let width = std::mem::size_of::<$ty>();
+                    let val = <$ty>::decode(bytes.get(..width)?)?;
+                    Some((val, width))
+                }
+            }
+        )*
+    };
+}
+
+key_component_for_fixed_width!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+impl<const N: usize> KeyComponent for [u8; N] {
+    fn encode_component(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self)
+    }
+}
+
+impl<const N: usize> FromKeyComponent for [u8; N] {
+    fn decode_component(bytes: &[u8]) -> Option<(Self, usize)> {
+        Some((bytes.get(..N)?.try_into().ok()?, N))
+    }
+}
+
+impl KeyComponent for [u8] {
+    fn encode_component(&self, out: &mut Vec<u8>) {
+        encode_escaped(self, out)
+    }
+}
+
+impl KeyComponent for Vec<u8> {
+    fn encode_component(&self, out: &mut Vec<u8>) {
+        encode_escaped(self, out)
+    }
+}
+
+impl FromKeyComponent for Vec<u8> {
+    fn decode_component(bytes: &[u8]) -> Option<(Self, usize)> {
+        decode_escaped(bytes)
+    }
+}
+
+impl KeyComponent for str {
+    fn encode_component(&self, out: &mut Vec<u8>) {
+        encode_escaped(self.as_bytes(), out)
+    }
+}
+
+impl KeyComponent for String {
+    fn encode_component(&self, out: &mut Vec<u8>) {
+        encode_escaped(self.as_bytes(), out)
+    }
+}
+
+impl FromKeyComponent for String {
+    fn decode_component(bytes: &[u8]) -> Option<(Self, usize)> {
+        let (bytes, used) = decode_escaped(bytes)?;
+        Some((String::from_utf8(bytes).ok()?, used))
+    }
+}
+
+impl KeyComponent for Key {
+    fn encode_component(&self, out: &mut Vec<u8>) {
+        encode_escaped(self.as_slice(), out)
+    }
+}
+
+impl FromKeyComponent for Key {
+    fn decode_component(bytes: &[u8]) -> Option<(Self, usize)> {
+        let (bytes, used) = decode_escaped(bytes)?;
+        Some((Key::from(bytes), used))
+    }
+}
+
+// a tuple is its components one after the other and decodes only if the
+// components use up every byte.
+macro_rules! art_key_for_tuple {
+    ($(($($name:ident),+)),+) => {
+        $(
+            impl<$($name: KeyComponent),+> ArtKey for ($($name,)+) {
+                #[allow(non_snake_case)]
+                fn encode(&self, out: &mut Vec<u8>) {
+                    let ($($name,)+) = self;
+                    $($name.encode_component(out);)+
+                }
+            }
+
+            impl<$($name: FromKeyComponent),+> FromArtKey for ($($name,)+) {
+                #[allow(non_snake_case)]
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    let mut rest = bytes;
+                    $(
+                        let ($name, used) = $name::decode_component(rest)?;
+                        rest = &rest[used..];
+                    )+
+                    if !rest.is_empty() {
+                        return None;
+                    }
+                    Some(($($name,)+))
+                }
+            }
+        )+
+    };
+}
+
+art_key_for_tuple!(
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F)
+);
+
+#[test]
+fn test_tuple_key_escapes_variable_length_components() {
+    assert_eq!(("ab",).to_key(), Key::from(&b"ab\x00\x01"[..]));
+    assert_eq!(
+        (&b"a\x00b"[..],).to_key(),
+        Key::from(&b"a\x00\xffb\x00\x01"[..])
+    );
+    assert_eq!((7u16, "").to_key(), Key::from(&[0, 7, 0, 1][..]));
+    let key = (b"\x00\x00".to_vec(), String::from("z\x00")).to_key();
+    assert_eq!(
+        key.decode::<(Vec<u8>, String)>(),
+        Some((b"\x00\x00".to_vec(), String::from("z\x00")))
+    );
+    // truncated, unterminated and trailing bytes are rejected.
+    assert_eq!(Key::from(&b"ab\x00"[..]).decode::<(String,)>(), None);
+    assert_eq!(Key::from(&b"ab"[..]).decode::<(String,)>(), None);
+    assert_eq!(Key::from(&b"ab\x00\x02"[..]).decode::<(String,)>(), None);
+    assert_eq!(Key::from(&b"ab\x00\x01c"[..]).decode::<(String,)>(), None);
+    assert_eq!(Key::from(&[0, 0, 1][..]).decode::<(u32,)>(), None);
+}
+
+#[test]
+fn test_tuple_key_sorts_like_the_tuple() {
+    let mut tuples = vec![];
+    for tenant in [0u32, 1, 256, u32::MAX] {
+        for name in [
+            "", "\x00", "\x00\x00", "\x01", "a", "a\x00", "a\x00b", "ab", "b",
+        ] {
+            for ts in [i64::MIN, -1, 0, 1, i64::MAX] {
+                tuples.push((tenant, String::from(name), ts));
+            }
+        }
+    }
+    let mut sorted = tuples.clone();
+    sorted.sort();
+    let mut by_key = tuples.clone();
+    by_key.sort_by_key(|t| t.to_key());
+    assert_eq!(by_key, sorted);
+    for tuple in tuples {
+        let key = tuple.to_key();
+        // a leading part of the tuple is a byte prefix of the whole.
+        assert!(key.starts_with(&(tuple.0,).to_key()));
+        assert!(key.starts_with(&(tuple.0, tuple.1.as_str()).to_key()));
+        assert_eq!(key.decode(), Some(tuple));
+    }
+}
+
+#[test]
+fn test_tuple_key_secondary_index_prefix_scan() {
+    use crate::TypedTree;
+
+    let mut index = TypedTree::<(u32, String, i64), ()>::new();
+    for tenant in 0..20u32 {
+        for name in ["alice", "bob", "bobby", "carol"] {
+            for ts in -3..3i64 {
+                index.insert(&(tenant, String::from(name), ts * 1_000), ());
+            }
+        }
+    }
+    let rows: Vec<(u32, String, i64)> = index.prefix_iter(&(7u32, "bob")).map(|(k, _)| k).collect();
+    let expected: Vec<(u32, String, i64)> = (-3..3)
+        .map(|ts| (7, String::from("bob"), ts * 1_000))
+        .collect();
+    assert_eq!(rows, expected);
+    assert_eq!(index.prefix_iter(&(7u32,)).count(), 24);
+    assert_eq!(index.prefix_iter(&(7u32, "bo")).count(), 0);
+    let last = index.prefix_iter(&(19u32,)).next_back().map(|(k, _)| k);
+    assert_eq!(last, Some((19, String::from("carol"), 2_000)));
+}
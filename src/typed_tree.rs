@@ -0,0 +1,316 @@
+use crate::{ArtKey, Entry, FromArtKey, IntoIter, Iter, IterMut, Key, PrefixIter, Range, Tree};
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+
+/// A `Tree` keyed by a type that encodes into binary-comparable bytes.
+///
+/// Keys are encoded with `ArtKey` on the way in and decoded with `FromArtKey` on
+/// the way out, so iteration follows the order of `K` rather than its bytes.
+///
+/// ```
+/// let mut tree = artoo::TypedTree::<i64, &str>::new();
+/// tree.insert(&3, "three");
+/// tree.insert(&-7, "minus seven");
+/// tree.insert(&0, "zero");
+/// let keys: Vec<i64> = tree.keys().collect();
+/// assert_eq!(keys, vec![-7, 0, 3]);
+/// ```
+pub struct TypedTree<K, V> {
+    tree: Tree<V>,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<K, V> TypedTree<K, V> {
+    pub fn new() -> TypedTree<K, V> {
+        TypedTree {
+            tree: Tree::new(),
+            _key: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// The byte-keyed tree holding the encoded keys.
+    pub fn as_tree(&self) -> &Tree<V> {
+        &self.tree
+    }
+
+    pub fn into_tree(self) -> Tree<V> {
+        self.tree
+    }
+}
+
+impl<K: ArtKey, V> TypedTree<K, V> {
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        key.with_encoded(|bytes| self.tree.get(bytes))
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        key.with_encoded(|bytes| self.tree.get_mut(bytes))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn insert<Q>(&mut self, key: &Q, val: V) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        key.with_encoded(|bytes| self.tree.insert(bytes, val))
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        key.with_encoded(|bytes| self.tree.remove(bytes))
+    }
+
+    /// Gets the entry for `key`. The key of the entry is the encoded key.
+    pub fn entry<Q>(&mut self, key: &Q) -> Entry<'_, V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        key.with_encoded(|bytes| self.tree.entry(bytes))
+    }
+}
+
+impl<K: FromArtKey, V> TypedTree<K, V> {
+    /// Iterates the entries in the order of `K`.
+    pub fn iter(&self) -> Decoded<K, Iter<'_, V>> {
+        Decoded::new(self.tree.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> Decoded<K, IterMut<'_, V>> {
+        Decoded::new(self.tree.iter_mut())
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = K> + '_ {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + '_ {
+        self.tree.values()
+    }
+
+    /// Iterates the entries whose keys start with the encoding of `prefix`.
+    ///
+    /// With tuple keys this scans every entry that shares the leading components.
+    ///
+    /// ```
+    /// let mut index = artoo::TypedTree::<(u32, String), ()>::new();
+    /// index.insert(&(1, String::from("a")), ());
+    /// index.insert(&(2, String::from("b")), ());
+    /// index.insert(&(2, String::from("c")), ());
+    /// assert_eq!(index.prefix_iter(&(2u32,)).count(), 2);
+    /// ```
+    pub fn prefix_iter<P: ArtKey + ?Sized>(&self, prefix: &P) -> Decoded<K, PrefixIter<'_, V>> {
+        prefix.with_encoded(|bytes| Decoded::new(self.tree.prefix_iter(bytes)))
+    }
+
+    /// Iterates the entries whose keys fall within `range`, in the order of `K`.
+    pub fn range<Q, R>(&self, range: R) -> Decoded<K, Range<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let lower = encode_bound(range.start_bound());
+        let upper = encode_bound(range.end_bound());
+        Decoded::new(
+            self.tree
+                .range::<[u8], _>((as_slice(&lower), as_slice(&upper))),
+        )
+    }
+}
+
+fn encode_bound<Q: ArtKey + ?Sized>(bound: Bound<&Q>) -> Bound<Key> {
+    bound.map(|key| key.to_key())
+}
+
+fn as_slice(bound: &Bound<Key>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.as_slice()),
+        Bound::Excluded(key) => Bound::Excluded(key.as_slice()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+impl<K, V> Default for TypedTree<K, V> {
+    fn default() -> Self {
+        TypedTree::new()
+    }
+}
+
+impl<K, V> From<Tree<V>> for TypedTree<K, V> {
+    /// Wraps a tree whose keys are already encodings of `K`.
+    fn from(tree: Tree<V>) -> Self {
+        TypedTree {
+            tree,
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<'a, K: FromArtKey, V> IntoIterator for &'a TypedTree<K, V> {
+    type Item = (K, &'a V);
+    type IntoIter = Decoded<K, Iter<'a, V>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: FromArtKey, V> IntoIterator for TypedTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = Decoded<K, IntoIter<V>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Decoded::new(self.tree.into_iter())
+    }
+}
+
+/// Decodes the keys of a byte-keyed iterator into `K`.
+///
+/// The keys of a `TypedTree` are always encodings of `K`, so a key that fails to
+/// decode means the tree was filled through `as_tree` or `From<Tree<V>>` with
+/// foreign keys, and panics.
+pub struct Decoded<K, I> {
+    inner: I,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<K, I> Decoded<K, I> {
+    pub(crate) fn new(inner: I) -> Decoded<K, I> {
+        Decoded {
+            inner,
+            _key: PhantomData,
+        }
+    }
+}
+
+fn decode<K: FromArtKey>(key: &Key) -> K {
+    match K::decode(key) {
+        Some(key) => key,
+        None => panic!("TypedTree key could not be decoded: {:?}", key),
+    }
+}
+
+impl<K: FromArtKey, T, I: Iterator<Item = (Key, T)>> Iterator for Decoded<K, I> {
+    type Item = (K, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, val) = self.inner.next()?;
+        Some((decode(&key), val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: FromArtKey, T, I: DoubleEndedIterator<Item = (Key, T)>> DoubleEndedIterator
+    for Decoded<K, I>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (key, val) = self.inner.next_back()?;
+        Some((decode(&key), val))
+    }
+}
+
+impl<K: FromArtKey, T, I: ExactSizeIterator<Item = (Key, T)>> ExactSizeIterator for Decoded<K, I> {}
+
+#[test]
+fn test_typed_tree_iterates_in_key_order() {
+    use std::collections::BTreeMap;
+
+    let mut tree = TypedTree::<i32, i32>::new();
+    let mut btree = BTreeMap::new();
+    for i in -5_000..5_000 {
+        let key = i * 7919 % 10_007;
+        assert_eq!(tree.insert(&key, i), btree.insert(key, i));
+    }
+    assert_eq!(tree.len(), btree.len());
+    assert!(tree.iter().map(|(k, v)| (k, *v)).eq(btree.clone()));
+    assert!(tree
+        .iter()
+        .rev()
+        .map(|(k, v)| (k, *v))
+        .eq(btree.clone().into_iter().rev()));
+    assert!(tree
+        .range(-100..=250)
+        .map(|(k, v)| (k, *v))
+        .eq(btree.range(-100..=250).map(|(k, v)| (*k, *v))));
+    assert!(tree
+        .range(..-9_000)
+        .map(|(k, _)| k)
+        .eq(btree.range(..-9_000).map(|(k, _)| *k)));
+    for (_, v) in tree.iter_mut() {
+        *v += 1;
+    }
+    assert_eq!(tree.get(&0), btree.get(&0).map(|v| v + 1).as_ref());
+    assert_eq!(tree.remove(&0), btree.remove(&0).map(|v| v + 1));
+    assert!(!tree.contains_key(&0));
+}
+
+#[test]
+fn test_typed_tree_string_keys() {
+    let mut tree = TypedTree::<String, usize>::new();
+    for word in ["pear", "apple", "fig", "applesauce", ""] {
+        tree.insert(word, word.len());
+    }
+    assert_eq!(tree.get("fig"), Some(&3));
+    assert_eq!(tree.get(&String::from("pear")), Some(&4));
+    *tree.entry("kiwi").or_default() += 10;
+    let keys: Vec<String> = tree.keys().collect();
+    assert_eq!(keys, vec!["", "apple", "applesauce", "fig", "kiwi", "pear"]);
+    let got: Vec<(String, usize)> = tree
+        .range::<str, _>((Bound::Included("b"), Bound::Excluded("l")))
+        .map(|(k, v)| (k, *v))
+        .collect();
+    assert_eq!(
+        got,
+        vec![(String::from("fig"), 3), (String::from("kiwi"), 10)]
+    );
+    let owned: Vec<(String, usize)> = tree.into_iter().collect();
+    assert_eq!(owned.len(), 6);
+}
+
+#[test]
+fn test_typed_tree_float_keys() {
+    let mut tree = TypedTree::<f64, ()>::new();
+    for f in [2.5, -0.0, f64::INFINITY, -3.0, 0.0, -f64::INFINITY, 1e-300] {
+        tree.insert(&f, ());
+    }
+    let keys: Vec<f64> = tree.keys().collect();
+    let mut expected = keys.clone();
+    expected.sort_by(f64::total_cmp);
+    assert_eq!(keys.len(), 7);
+    assert!(keys
+        .iter()
+        .zip(&expected)
+        .all(|(a, b)| a.to_bits() == b.to_bits()));
+}